@@ -1,68 +1,46 @@
-use glam::Vec3;
-use rand::random;
+use rand::thread_rng;
 use raytracing::{
-    camera::Camera,
+    bvh::{AabbHittable, BvhNode},
+    camera::{ray_color, Camera},
     material::{Dielectric, Lambertian, Material, Metal},
-    Sphere, World,
+    math::{Real, Vec3},
+    MaterialRef, Sphere,
 };
+use rt_core::scene::{final_scene, MaterialDesc};
 
 // Ideal aspect ratio
-const ASPECT_RATIO: f32 = 16.0 / 9.0;
+const ASPECT_RATIO: Real = 16.0 / 9.0;
 
 fn main() {
     // Setup world
-    let mut world: World = vec![Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))),
-    ))];
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let center = Vec3::new(
-                a as f32 + 0.9 * random::<f32>(),
-                0.2,
-                b as f32 + 0.9 * random::<f32>(),
-            );
-
-            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                let choose_mat = random::<f32>();
-
-                let albedo = Vec3::new(random::<f32>(), random::<f32>(), random::<f32>())
-                    * Vec3::new(random::<f32>(), random::<f32>(), random::<f32>());
-                let material: Box<dyn Material + Send + Sync> = if choose_mat < 0.8 {
-                    Box::new(Lambertian::new(albedo))
-                } else if choose_mat < 0.95 {
-                    Box::new(Metal::new(albedo).fuzz(random::<f32>() * 0.5))
-                } else {
-                    Box::new(Dielectric::new(1.5))
-                };
-
-                world.push(Box::new(Sphere::new(center, 0.2, material)));
+    let mut world: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> =
+        vec![Box::new(Sphere::new(
+            Vec3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            Box::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))),
+        ))];
+
+    for sphere in final_scene(&mut thread_rng()) {
+        let material: Box<dyn Material + Send + Sync> = match sphere.material {
+            MaterialDesc::Lambertian { albedo } => Box::new(Lambertian::new(albedo)),
+            MaterialDesc::Metal { albedo, fuzz } => Box::new(Metal::new(albedo).fuzz(fuzz)),
+            MaterialDesc::Dielectric { refraction_index } => {
+                Box::new(Dielectric::new(refraction_index))
             }
-        }
+        };
+        world.push(Box::new(Sphere::new(
+            sphere.center,
+            sphere.radius,
+            material,
+        )));
     }
 
-    world.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        Box::new(Dielectric::new(1.5)),
-    )));
-    world.push(Box::new(Sphere::new(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        Box::new(Lambertian::new(Vec3::new(0.4, 0.2, 0.1))),
-    )));
-    world.push(Box::new(Sphere::new(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        Box::new(Metal::new(Vec3::new(0.7, 0.6, 0.5)).fuzz(0.0)),
-    )));
+    let world = BvhNode::from_objects(world);
 
     // Image
     let image_width = 1280;
-    let image_height = (image_width as f32 / ASPECT_RATIO) as usize;
-    let aspect_ratio = image_width as f32 / image_height as f32; // real aspect ratio
+    let image_height = (image_width as Real / ASPECT_RATIO) as usize;
+    let aspect_ratio = image_width as Real / image_height as Real; // real aspect ratio
 
     let camera = Camera::new(aspect_ratio)
         .samples_per_pixel(500)
@@ -72,5 +50,7 @@ fn main() {
         .focus_distance(10.0)
         .defocus_angle(0.6);
 
-    camera.render_to_path(&world, image_width, "image.png");
+    camera.render_to_path(image_width, "image.png", |ray, depth| {
+        ray_color(ray, &world, depth)
+    });
 }
@@ -3,18 +3,12 @@ use std::{
     io::{BufWriter, Write},
 };
 
-use env_logger::Env;
-use indicatif::{MultiProgress, ProgressBar};
-use indicatif_log_bridge::LogWrapper;
+use indicatif::ProgressBar;
 use log::info;
+use rt_core::log::logger;
 
 fn main() {
-    let logger = env_logger::Builder::from_env(Env::default().default_filter_or("info")).build();
-    let level = logger.filter();
-    let multi = MultiProgress::new();
-
-    LogWrapper::new(multi.clone(), logger).try_init().unwrap();
-    log::set_max_level(level);
+    let multi = logger().multi();
 
     // Image
     let image_width = 256;
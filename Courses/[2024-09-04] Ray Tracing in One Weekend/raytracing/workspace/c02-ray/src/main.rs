@@ -4,13 +4,12 @@ use std::{
 };
 
 use c02_ray::Ray;
-use env_logger::Env;
-use glam::Vec3;
-use indicatif::{MultiProgress, ProgressBar};
-use indicatif_log_bridge::LogWrapper;
+use indicatif::ProgressBar;
 use log::info;
+use rt_core::log::logger;
+use rt_core::math::{Real, Vec3};
 
-const ASPECT_RATIO: f32 = 16.0 / 9.0;
+const ASPECT_RATIO: Real = 16.0 / 9.0;
 
 pub fn ray_color(ray: &Ray) -> Vec3 {
     let unit_direction = ray.direction.normalize();
@@ -19,27 +18,22 @@ pub fn ray_color(ray: &Ray) -> Vec3 {
 }
 
 fn main() {
-    let logger = env_logger::Builder::from_env(Env::default().default_filter_or("info")).build();
-    let level = logger.filter();
-    let multi = MultiProgress::new();
-
-    LogWrapper::new(multi.clone(), logger).try_init().unwrap();
-    log::set_max_level(level);
+    let multi = logger().multi();
 
     // Image
     let image_width = 400;
-    let image_height = ((image_width as f32 / ASPECT_RATIO) as u32).max(1);
+    let image_height = ((image_width as Real / ASPECT_RATIO) as u32).max(1);
 
     // Camera
     let focal_length = 1.0;
     let viewport_height = 2.0;
-    let viewport_width = viewport_height as f32 * (image_width as f32 / image_height as f32);
+    let viewport_width = viewport_height * (image_width as Real / image_height as Real);
     let camera_center = Vec3::new(0.0, 0.0, 0.0);
     let viewport_u = Vec3::new(viewport_width, 0.0, 0.0);
     let viewport_v = Vec3::new(0.0, -viewport_height, 0.0);
 
-    let pixel_delta_u = viewport_u / image_width as f32;
-    let pixel_delta_v = viewport_v / image_height as f32;
+    let pixel_delta_u = viewport_u / image_width as Real;
+    let pixel_delta_v = viewport_v / image_height as Real;
 
     let viewport_upper_left =
         camera_center - Vec3::new(0.0, 0.0, focal_length) - viewport_u / 2.0 - viewport_v / 2.0;
@@ -58,7 +52,7 @@ fn main() {
     for j in 0..image_height {
         for i in 0..image_width {
             let pixel_center =
-                pixel00_loc + (i as f32 * pixel_delta_u) + (j as f32 * pixel_delta_v);
+                pixel00_loc + (i as Real * pixel_delta_u) + (j as Real * pixel_delta_v);
             let ray = Ray::new(camera_center, pixel_center - camera_center);
 
             let color = ray_color(&ray);
@@ -1,44 +1,22 @@
-use glam::Vec3;
+use std::ops::Range;
 
-pub struct Ray {
-    pub origin: Vec3,
-    pub direction: Vec3,
-}
-
-impl Ray {
-    pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Ray { origin, direction }
-    }
+use rt_core::math::{Real, Vec3};
 
-    pub fn at(&self, t: f32) -> Vec3 {
-        self.origin + self.direction * t
-    }
-}
-
-pub struct HitRecord {
-    pub point: Vec3,
-    pub normal: Vec3,
-    pub t: f32,
-    pub front_face: bool,
-}
-
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
-}
+pub use rt_core::{HitRecord, Hittable, Ray};
 
 pub struct Sphere {
     center: Vec3,
-    radius: f32,
+    radius: Real,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32) -> Self {
+    pub fn new(center: Vec3, radius: Real) -> Self {
         Sphere { center, radius }
     }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
         let oc = ray.origin - self.center;
         let a = ray.direction.dot(ray.direction);
         let b = oc.dot(ray.direction);
@@ -50,10 +28,10 @@ impl Hittable for Sphere {
 
         let sqrtd = discriminant.sqrt();
         let mut t = (-b - sqrtd) / a;
-        if t - t_min < 0.0 || t_max - t < 0.0 {
+        if t - t_range.start < 0.0 || t_range.end - t < 0.0 {
             t = (-b + sqrtd) / a;
         }
-        if t - t_min < 0.0 || t_max - t < 0.0 {
+        if t - t_range.start < 0.0 || t_range.end - t < 0.0 {
             return None;
         }
 
@@ -68,22 +46,14 @@ impl Hittable for Sphere {
             normal,
             t,
             front_face,
+            material: None,
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            footprint: 0.0,
         })
     }
 }
 
-pub type World = Vec<Box<dyn Hittable>>;
-
-impl Hittable for World {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let mut closest = t_max;
-        let mut hit_record = None;
-        for object in self.iter() {
-            if let Some(record) = object.hit(ray, t_min, closest) {
-                closest = record.t;
-                hit_record = Some(record);
-            }
-        }
-        hit_record
-    }
-}
+pub type World = Vec<Box<dyn Hittable + Send + Sync>>;
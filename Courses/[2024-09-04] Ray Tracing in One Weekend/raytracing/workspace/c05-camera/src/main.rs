@@ -1,8 +1,11 @@
-use c05_camera::{camera::Camera, Sphere, World};
-use glam::Vec3;
+use c05_camera::{
+    camera::{ray_color, Camera},
+    Sphere, World,
+};
+use rt_core::math::{Real, Vec3};
 
 // Ideal aspect ratio
-const ASPECT_RATIO: f32 = 16.0 / 9.0;
+const ASPECT_RATIO: Real = 16.0 / 9.0;
 
 fn main() {
     // Setup world
@@ -13,11 +16,16 @@ fn main() {
 
     // Image
     let image_width = 400;
-    let image_height = (image_width as f32 / ASPECT_RATIO) as usize;
+    let image_height = (image_width as Real / ASPECT_RATIO) as usize;
     // let (image_width, image_height) = (400, 225);
-    let aspect_ratio = image_width as f32 / image_height as f32; // real aspect ratio
+    let aspect_ratio = image_width as Real / image_height as Real; // real aspect ratio
 
-    let camera = Camera::new(aspect_ratio);
+    // one ray through every pixel center, no gamma correction yet
+    let camera = Camera::new(aspect_ratio)
+        .samples_per_pixel(1)
+        .gamma_correction(false);
 
-    camera.render_to_ppm(&world, image_width, "image_c05.ppm");
+    camera.render_to_path(image_width, "image_c05.ppm", |ray, _| {
+        ray_color(ray, &world)
+    });
 }
@@ -1,51 +1,24 @@
-pub mod log;
 pub mod camera;
-pub mod utils;
 
 use std::ops::Range;
 
-use glam::Vec3;
+use rt_core::math::{Real, Vec3};
 
-pub struct Ray {
-    pub origin: Vec3,
-    pub direction: Vec3,
-}
-
-impl Ray {
-    pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Ray { origin, direction }
-    }
-
-    pub fn at(&self, t: f32) -> Vec3 {
-        self.origin + self.direction * t
-    }
-}
-
-pub struct HitRecord {
-    pub point: Vec3,
-    /// Unit normal vector
-    pub normal: Vec3,
-    pub t: f32,
-    pub front_face: bool,
-}
-
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord>;
-}
+pub use rt_core::{log, utils, HitRecord, Hittable, Ray};
 
 pub struct Sphere {
     center: Vec3,
-    radius: f32,
+    radius: Real,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32) -> Self {
+    pub fn new(center: Vec3, radius: Real) -> Self {
         Sphere { center, radius }
     }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
         let oc = ray.origin - self.center;
         let a = ray.direction.dot(ray.direction);
         let b = oc.dot(ray.direction);
@@ -75,22 +48,14 @@ impl Hittable for Sphere {
             normal,
             t,
             front_face,
+            material: None,
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            footprint: 0.0,
         })
     }
 }
 
 pub type World = Vec<Box<dyn Hittable + Send + Sync>>;
-
-impl Hittable for World {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
-        let mut closest = t_range.end;
-        let mut hit_record = None;
-        for object in self.iter() {
-            if let Some(record) = object.hit(ray, t_range.start..closest) {
-                closest = record.t;
-                hit_record = Some(record);
-            }
-        }
-        hit_record
-    }
-}
@@ -1,8 +1,11 @@
-use c08_gamma_correction::{camera::Camera, Sphere, World};
-use glam::Vec3;
+use c08_gamma_correction::{
+    camera::{ray_color, Camera},
+    Sphere, World,
+};
+use rt_core::math::{Real, Vec3};
 
 // Ideal aspect ratio
-const ASPECT_RATIO: f32 = 16.0 / 9.0;
+const ASPECT_RATIO: Real = 16.0 / 9.0;
 
 fn main() {
     // Setup world
@@ -14,11 +17,13 @@ fn main() {
     // Image
     let image_width = 400;
     // let image_width = 1280;
-    let image_height = (image_width as f32 / ASPECT_RATIO) as usize;
+    let image_height = (image_width as Real / ASPECT_RATIO) as usize;
     // let (image_width, image_height) = (400, 225);
-    let aspect_ratio = image_width as f32 / image_height as f32; // real aspect ratio
+    let aspect_ratio = image_width as Real / image_height as Real; // real aspect ratio
 
     let camera = Camera::new(aspect_ratio);
 
-    camera.render_to_path(&world, image_width, "image_c08.png");
+    camera.render_to_path(image_width, "image_c08.png", |ray, depth| {
+        ray_color(ray, &world, depth)
+    });
 }
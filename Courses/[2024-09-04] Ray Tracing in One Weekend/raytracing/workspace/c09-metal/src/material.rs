@@ -1,4 +1,5 @@
-use glam::Vec3;
+use rand::thread_rng;
+use rt_core::math::{Real, Vec3};
 
 use crate::{utils::random_in_unit_sphere, HitRecord, Ray};
 
@@ -18,8 +19,8 @@ impl Lambertian {
 
 impl Material for Lambertian {
     fn scatter(&self, _ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)> {
-        let mut scatter_direction = record.normal + random_in_unit_sphere();
-        if scatter_direction.length_squared() <= f32::EPSILON {
+        let mut scatter_direction = record.normal + random_in_unit_sphere(&mut thread_rng());
+        if scatter_direction.length_squared() <= Real::EPSILON {
             scatter_direction = record.normal;
         }
 
@@ -31,7 +32,7 @@ impl Material for Lambertian {
 #[derive(Default)]
 pub struct Metal {
     albedo: Vec3,
-    fuzz: f32,
+    fuzz: Real,
 }
 
 impl Metal {
@@ -42,7 +43,7 @@ impl Metal {
         }
     }
 
-    pub fn fuzz(mut self, fuzz: f32) -> Self {
+    pub fn fuzz(mut self, fuzz: Real) -> Self {
         self.fuzz = fuzz;
         self
     }
@@ -51,7 +52,8 @@ impl Metal {
 impl Material for Metal {
     fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)> {
         let reflected = ray.direction.reflect(record.normal);
-        let reflected = reflected.normalize() + self.fuzz * random_in_unit_sphere();
+        let reflected =
+            reflected.normalize() + self.fuzz * random_in_unit_sphere(&mut thread_rng());
 
         if reflected.dot(record.normal) > 0.0 {
             let scattered_ray = Ray::new(record.point, reflected);
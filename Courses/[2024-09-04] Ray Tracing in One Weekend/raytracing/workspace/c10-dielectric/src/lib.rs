@@ -1,56 +1,32 @@
-pub mod log;
 pub mod camera;
-pub mod utils;
 pub mod material;
 
 use std::{ops::Range, sync::Arc};
 
-use glam::Vec3;
 use material::Material;
+use rt_core::math::{Real, Vec3};
 
-pub struct Ray {
-    pub origin: Vec3,
-    pub direction: Vec3,
-}
-
-impl Ray {
-    pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Ray { origin, direction }
-    }
-
-    pub fn at(&self, t: f32) -> Vec3 {
-        self.origin + self.direction * t
-    }
-}
+pub use rt_core::{log, utils, Hittable, Ray};
 
-pub struct HitRecord {
-    pub point: Vec3,
-    /// Unit normal vector
-    pub normal: Vec3,
-    pub t: f32,
-    pub front_face: bool,
-    pub material: Arc<Box<dyn Material + Send + Sync>>,
-}
+pub type MaterialRef = Arc<Box<dyn Material + Send + Sync>>;
 
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord>;
-}
+pub type HitRecord = rt_core::HitRecord<MaterialRef>;
 
 pub struct Sphere {
     center: Vec3,
-    radius: f32,
-    material: Arc<Box<dyn Material + Send + Sync>>,
+    radius: Real,
+    material: MaterialRef,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32, material: Box<dyn Material + Send + Sync>) -> Self {
+    pub fn new(center: Vec3, radius: Real, material: Box<dyn Material + Send + Sync>) -> Self {
         let material = Arc::new(material);
         Sphere { center, radius, material }
     }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
+impl Hittable<MaterialRef> for Sphere {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
         let oc = ray.origin - self.center;
         let a = ray.direction.dot(ray.direction);
         let b = oc.dot(ray.direction);
@@ -80,23 +56,14 @@ impl Hittable for Sphere {
             normal,
             t,
             front_face,
-            material: self.material.clone(),
+            material: Some(self.material.clone()),
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            footprint: 0.0,
         })
     }
 }
 
-pub type World = Vec<Box<dyn Hittable + Send + Sync>>;
-
-impl Hittable for World {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
-        let mut closest = t_range.end;
-        let mut hit_record = None;
-        for object in self.iter() {
-            if let Some(record) = object.hit(ray, t_range.start..closest) {
-                closest = record.t;
-                hit_record = Some(record);
-            }
-        }
-        hit_record
-    }
-}
+pub type World = Vec<Box<dyn Hittable<MaterialRef> + Send + Sync>>;
@@ -1,12 +1,12 @@
 use c11_camera_pro::{
-    camera::Camera,
+    camera::{ray_color, Camera},
     material::{Dielectric, Lambertian, Metal},
     Sphere, World,
 };
-use glam::Vec3;
+use rt_core::math::{Real, Vec3};
 
 // Ideal aspect ratio
-const ASPECT_RATIO: f32 = 16.0 / 9.0;
+const ASPECT_RATIO: Real = 16.0 / 9.0;
 
 fn main() {
     // Setup world
@@ -59,19 +59,25 @@ fn main() {
     // Image
     // let image_width = 400;
     let image_width = 1280;
-    let image_height = (image_width as f32 / ASPECT_RATIO) as usize;
+    let image_height = (image_width as Real / ASPECT_RATIO) as usize;
     // let (image_width, image_height) = (400, 225);
-    let aspect_ratio = image_width as f32 / image_height as f32; // real aspect ratio
+    let aspect_ratio = image_width as Real / image_height as Real; // real aspect ratio
 
     let mut camera = Camera::new(aspect_ratio)
         .pos(Vec3::new(-1.0, 1.0, 1.0))
         .look_at(Vec3::new(0.1, 0.03, -0.64));
 
-    camera.render_to_path(&world, image_width, "image_c11-fov90.png");
+    camera.render_to_path(image_width, "image_c11-fov90.png", |ray, depth| {
+        ray_color(ray, &world, depth)
+    });
 
     camera.set_fov(30.0);
-    camera.render_to_path(&world, image_width, "image_c11-fov30.png");
+    camera.render_to_path(image_width, "image_c11-fov30.png", |ray, depth| {
+        ray_color(ray, &world, depth)
+    });
 
     camera.set_fov(150.0);
-    camera.render_to_path(&world, image_width, "image_c11-fov150.png");
+    camera.render_to_path(image_width, "image_c11-fov150.png", |ray, depth| {
+        ray_color(ray, &world, depth)
+    });
 }
@@ -1,5 +1,5 @@
-use glam::Vec3;
-use rand::random;
+use rand::{random, thread_rng};
+use rt_core::math::{Real, Vec3};
 
 use crate::{
     utils::{random_in_unit_sphere, reflectance, refract},
@@ -22,8 +22,8 @@ impl Lambertian {
 
 impl Material for Lambertian {
     fn scatter(&self, _ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)> {
-        let mut scatter_direction = record.normal + random_in_unit_sphere();
-        if scatter_direction.length_squared() <= f32::EPSILON {
+        let mut scatter_direction = record.normal + random_in_unit_sphere(&mut thread_rng());
+        if scatter_direction.length_squared() <= Real::EPSILON {
             scatter_direction = record.normal;
         }
 
@@ -35,7 +35,7 @@ impl Material for Lambertian {
 #[derive(Default)]
 pub struct Metal {
     albedo: Vec3,
-    fuzz: f32,
+    fuzz: Real,
 }
 
 impl Metal {
@@ -46,7 +46,7 @@ impl Metal {
         }
     }
 
-    pub fn fuzz(mut self, fuzz: f32) -> Self {
+    pub fn fuzz(mut self, fuzz: Real) -> Self {
         self.fuzz = fuzz;
         self
     }
@@ -55,7 +55,8 @@ impl Metal {
 impl Material for Metal {
     fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)> {
         let reflected = ray.direction.reflect(record.normal);
-        let reflected = reflected.normalize() + self.fuzz * random_in_unit_sphere();
+        let reflected =
+            reflected.normalize() + self.fuzz * random_in_unit_sphere(&mut thread_rng());
 
         if reflected.dot(record.normal) > 0.0 {
             let scattered_ray = Ray::new(record.point, reflected);
@@ -67,7 +68,7 @@ impl Material for Metal {
 }
 
 pub struct Dielectric {
-    refraction_index: f32,
+    refraction_index: Real,
 }
 
 impl Default for Dielectric {
@@ -79,7 +80,7 @@ impl Default for Dielectric {
 }
 
 impl Dielectric {
-    pub fn new(refraction_index: f32) -> Self {
+    pub fn new(refraction_index: Real) -> Self {
         Dielectric { refraction_index }
     }
 }
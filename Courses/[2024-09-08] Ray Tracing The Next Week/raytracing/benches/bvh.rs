@@ -0,0 +1,59 @@
+//! The median split [`BvhNode`] against the SAH [`Bvh`] on the spheres of `world()`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use raytracing::{
+    math::{Real, Vec3},
+    scene::builtin,
+    world::bvh::{Bvh, BvhNode},
+    Hittable, MaterialRef, Ray,
+};
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    group.bench_function("BvhNode", |b| {
+        b.iter_with_setup(builtin::world_objects, BvhNode::from_objects)
+    });
+    group.bench_function("Bvh", |b| {
+        b.iter_with_setup(builtin::world_objects, Bvh::from_objects)
+    });
+    group.finish();
+}
+
+/// Rays from around the camera of `world()` into the spheres
+fn rays() -> Vec<Ray> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..10_000)
+        .map(|_| {
+            let origin =
+                Vec3::new(13.0, 2.0, 3.0) + 0.5 * Vec3::new(rng.gen(), rng.gen(), rng.gen());
+            let target = Vec3::new(
+                rng.gen_range(-11.0..11.0),
+                rng.gen_range(0.0..1.0),
+                rng.gen_range(-11.0..11.0),
+            );
+            Ray::new(origin, target - origin)
+        })
+        .collect()
+}
+
+fn trace(c: &mut Criterion) {
+    let rays = rays();
+    let trace_all = |world: &dyn Hittable<MaterialRef>| {
+        rays.iter()
+            .filter(|ray| world.hit(ray, 0.001..Real::INFINITY).is_some())
+            .count()
+    };
+
+    let bvh_node = BvhNode::from_objects(builtin::world_objects());
+    let bvh = Bvh::from_objects(builtin::world_objects());
+    assert_eq!(trace_all(&bvh_node), trace_all(&bvh));
+
+    let mut group = c.benchmark_group("trace 10k rays");
+    group.bench_function("BvhNode", |b| b.iter(|| trace_all(black_box(&bvh_node))));
+    group.bench_function("Bvh", |b| b.iter(|| trace_all(black_box(&bvh))));
+    group.finish();
+}
+
+criterion_group!(benches, build, trace);
+criterion_main!(benches);
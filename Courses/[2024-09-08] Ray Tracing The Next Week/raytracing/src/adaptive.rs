@@ -0,0 +1,221 @@
+//! Adaptive sampling, see [`Camera::adaptive`](crate::camera::Camera::adaptive)
+//!
+//! Every pixel keeps taking samples until the confidence interval of its mean luminance is
+//! narrow enough, so flat regions stop early while noisy ones get up to the full
+//! [`samples_per_pixel`](crate::camera::Camera::samples_per_pixel).
+
+use image::{Rgb, RgbImage};
+use serde::Deserialize;
+
+use crate::math::{Real, Vec3};
+
+/// When a pixel has enough samples
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveSampling {
+    /// Samples every pixel takes before its variance is trusted
+    pub min_samples: u32,
+    /// A pixel is done once the 95% confidence interval of its mean luminance is within this
+    /// fraction of the mean
+    pub threshold: Real,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        AdaptiveSampling {
+            min_samples: 16,
+            threshold: 0.05,
+        }
+    }
+}
+
+impl AdaptiveSampling {
+    /// z-score of a two-sided 95% confidence interval
+    const Z: Real = 1.96;
+    /// Darker pixels are held to the error allowed at this luminance, so that noise nobody can
+    /// see does not use up the whole budget
+    const MIN_LUMINANCE: Real = 0.01;
+
+    pub fn converged(&self, stats: &PixelStats) -> bool {
+        if stats.samples() < self.min_samples.max(2) {
+            return false;
+        }
+        let half_width = Self::Z * (stats.variance() / stats.samples() as Real).sqrt();
+        half_width <= self.threshold * stats.mean_luminance().max(Self::MIN_LUMINANCE)
+    }
+}
+
+/// Running mean of the samples of a pixel, and the variance of their luminance
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelStats {
+    samples: u32,
+    sum: Vec3,
+    mean_luminance: Real,
+    /// sum of squared differences from the mean luminance (Welford's algorithm)
+    m2: Real,
+}
+
+impl PixelStats {
+    pub fn add(&mut self, color: Vec3) {
+        self.samples += 1;
+        self.sum += color;
+
+        let luminance = luminance(color);
+        let delta = luminance - self.mean_luminance;
+        self.mean_luminance += delta / self.samples as Real;
+        self.m2 += delta * (luminance - self.mean_luminance);
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn mean(&self) -> Vec3 {
+        self.sum / self.samples as Real
+    }
+
+    pub fn mean_luminance(&self) -> Real {
+        self.mean_luminance
+    }
+
+    /// Unbiased sample variance of the luminance
+    pub fn variance(&self) -> Real {
+        if self.samples < 2 {
+            return 0.0;
+        }
+        self.m2 / (self.samples - 1) as Real
+    }
+}
+
+/// Relative luminance of linear Rec. 709 (sRGB) colors
+pub fn luminance(color: Vec3) -> Real {
+    color.dot(Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+/// How many samples every pixel of a render took
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleCounts {
+    width: u32,
+    height: u32,
+    /// the most samples a pixel could have taken
+    max_samples: u32,
+    /// row major
+    counts: Vec<u32>,
+}
+
+impl SampleCounts {
+    pub fn new(width: u32, height: u32, max_samples: u32) -> Self {
+        SampleCounts {
+            width,
+            height,
+            max_samples,
+            counts: vec![0; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        self.counts[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, samples: u32) {
+        self.counts[(y * self.width + x) as usize] = samples;
+    }
+
+    pub fn mean(&self) -> Real {
+        let total = self.counts.iter().map(|&count| count as u64).sum::<u64>();
+        total as Real / self.counts.len().max(1) as Real
+    }
+
+    /// Black for no samples through red and yellow to white for the maximum
+    pub fn heatmap(&self) -> RgbImage {
+        const STOPS: [Vec3; 4] = [
+            Vec3::ZERO,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::ONE,
+        ];
+
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let t = self.get(x, y) as Real / self.max_samples.max(1) as Real;
+            let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as Real;
+            let i = (t as usize).min(STOPS.len() - 2);
+            let color = STOPS[i].lerp(STOPS[i + 1], t - i as Real);
+            let color_u8 = (255.999 * color).clamp(Vec3::ZERO, Vec3::splat(255.0));
+            Rgb([color_u8.x as u8, color_u8.y as u8, color_u8.z as u8])
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        background::Gradient, camera::Camera, material::Lambertian, primitive::Sphere,
+        texture::SolidColor, world::list::List,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_pixel_stats() {
+        let mut stats = PixelStats::default();
+        for luminance in [1.0, 2.0, 3.0, 6.0] {
+            stats.add(Vec3::splat(luminance));
+        }
+        assert_eq!(stats.samples(), 4);
+        assert!(stats.mean().abs_diff_eq(Vec3::splat(3.0), 1e-6));
+        assert!((stats.mean_luminance() - 3.0).abs() < 1e-5);
+        assert!((stats.variance() - 14.0 / 3.0).abs() < 1e-5);
+
+        let adaptive = AdaptiveSampling {
+            min_samples: 4,
+            threshold: 0.05,
+        };
+        assert!(!adaptive.converged(&stats));
+        let mut flat = PixelStats::default();
+        for _ in 0..3 {
+            flat.add(Vec3::splat(0.5));
+            assert!(!adaptive.converged(&flat));
+        }
+        flat.add(Vec3::splat(0.5));
+        assert!(adaptive.converged(&flat));
+    }
+
+    #[test]
+    fn test_adaptive_render() {
+        // a diffuse sphere in the middle of the sky, the sky needs no more than the minimum
+        let world = List::from_objects(vec![Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -1.0),
+            0.5,
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::splat(0.5)),
+            ))))),
+        ))]);
+        let camera = Camera::new(1.0)
+            .background(Arc::new(Box::new(Gradient::default())))
+            .samples_per_pixel(256)
+            .adaptive(AdaptiveSampling {
+                min_samples: 8,
+                threshold: 0.01,
+            });
+
+        let (image, counts) = camera.render_with_sample_counts(&world, 16);
+        assert_eq!((counts.width(), counts.height()), image.dimensions());
+        assert_eq!(counts.get(0, 0), 8);
+        assert!(counts.get(8, 8) > 8);
+        assert!((0..16).all(|y| (0..16).all(|x| counts.get(x, y) <= 256)));
+        assert!(counts.mean() < 256.0);
+
+        let heatmap = counts.heatmap();
+        assert_eq!(heatmap.get_pixel(0, 0), &Rgb([23, 0, 0]));
+    }
+}
@@ -0,0 +1,386 @@
+//! What a ray sees when it escapes the scene without hitting anything
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::Arc,
+};
+
+use fnv::FnvHasher;
+use image::{ImageResult, Rgb32FImage};
+use rand::{Rng, RngCore};
+
+use crate::{
+    adaptive::luminance,
+    math::{consts::PI, Quat, Real, Vec3},
+    primitive::Sphere,
+    texture::{ColorSpace, Filter, ImageTexture, Texture, Wrap},
+};
+
+pub type BackgroundRef = Arc<Box<dyn Background + Send + Sync>>;
+
+pub trait Background: fmt::Debug {
+    /// The radiance arriving from `direction`
+    fn color(&self, direction: Vec3) -> Vec3;
+
+    /// Whether [`Background::random`] is worth sending rays after, only backgrounds with
+    /// bright spots like the sun implement it
+    fn importance_sampled(&self) -> bool {
+        false
+    }
+
+    /// Density of sampling `direction` with [`Background::random`]
+    fn pdf_value(&self, _direction: Vec3) -> Real {
+        0.0
+    }
+
+    /// A random direction, more likely towards the bright parts
+    fn random(&self, _rng: &mut dyn RngCore) -> Vec3 {
+        Vec3::Y
+    }
+
+    /// Feeds every setting into `state`, see [`Camera::hash_settings`](crate::camera::Camera::hash_settings)
+    fn hash_settings(&self, state: &mut dyn Hasher);
+}
+
+/// The same color in every direction, use [`Vec3::ZERO`] for closed rooms lit only by lights
+#[derive(Debug, Clone, Copy)]
+pub struct Color(pub Vec3);
+
+impl Background for Color {
+    fn color(&self, _direction: Vec3) -> Vec3 {
+        self.0
+    }
+
+    fn hash_settings(&self, mut state: &mut dyn Hasher) {
+        self.0.to_array().map(Real::to_bits).hash(&mut state);
+    }
+}
+
+/// Blends from `bottom` when looking straight down to `top` when looking straight up
+#[derive(Debug, Clone, Copy)]
+pub struct Gradient {
+    pub bottom: Vec3,
+    pub top: Vec3,
+}
+
+/// The white-to-blue sky of the books
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            bottom: Vec3::ONE,
+            top: Vec3::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Background for Gradient {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let a = 0.5 * (direction.normalize().y + 1.0); // 从 [-1, 1] 映射到 [0, 1]
+        self.bottom.lerp(self.top, a)
+    }
+
+    fn hash_settings(&self, mut state: &mut dyn Hasher) {
+        [self.bottom, self.top]
+            .map(|v| v.to_array().map(Real::to_bits))
+            .hash(&mut state);
+    }
+}
+
+/// A piecewise constant density over `[0, 1)`, one piece per weight
+struct Distribution {
+    weights: Vec<Real>,
+    /// `cdf[i]` is the sum of the weights up to and including `i`
+    cdf: Vec<Real>,
+    total: Real,
+}
+
+impl Distribution {
+    fn new(weights: Vec<Real>) -> Self {
+        let cdf: Vec<Real> = weights
+            .iter()
+            .scan(0.0, |sum, w| {
+                *sum += w;
+                Some(*sum)
+            })
+            .collect();
+        let total = cdf.last().copied().unwrap_or(0.0);
+        Self {
+            weights,
+            cdf,
+            total,
+        }
+    }
+
+    /// Turns a uniform `xi` in `[0, 1)` into a position following the density, and the
+    /// piece it falls into
+    fn sample(&self, xi: Real) -> (Real, usize) {
+        let target = xi * self.total;
+        let i = self
+            .cdf
+            .partition_point(|&c| c <= target)
+            .min(self.weights.len() - 1);
+        let start = if i == 0 { 0.0 } else { self.cdf[i - 1] };
+        let offset = if self.weights[i] > 0.0 {
+            ((target - start) / self.weights[i]).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+        ((i as Real + offset) / self.weights.len() as Real, i)
+    }
+
+    /// The density within piece `i`
+    fn density(&self, i: usize) -> Real {
+        self.weights[i] * self.weights.len() as Real / self.total
+    }
+}
+
+/// An equirectangular (latitude-longitude) image around the scene, mapped like
+/// [`Sphere::get_uv`] maps the points of a sphere, e.g. an HDR photo of the sky
+pub struct EnvironmentMap {
+    texture: ImageTexture,
+    width: usize,
+    height: usize,
+    /// Digest of the texels, tells apart images of the same size
+    texels_hash: u64,
+    /// Chooses a row, weighted by the luminance of the row and the solid angle it covers
+    rows: Distribution,
+    /// Chooses a texel within each row
+    columns: Vec<Distribution>,
+    /// Degrees around the y axis
+    rotation: Real,
+    to_world: Quat,
+    intensity: Real,
+}
+
+impl EnvironmentMap {
+    /// `image` is decoded from `color_space` into linear radiance, HDR and EXR files are
+    /// linear already. Fails for an image without pixels.
+    pub fn new(image: Rgb32FImage, color_space: ColorSpace) -> ImageResult<Self> {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let texture = ImageTexture::new(image, color_space)?
+            .filter(Filter::Bilinear)
+            .wrap(Wrap::Repeat);
+
+        let mut hasher = FnvHasher::default();
+        for texel in texture.texels() {
+            texel.to_array().map(Real::to_bits).hash(&mut hasher);
+        }
+        let texels_hash = hasher.finish();
+
+        let luminances: Vec<Real> = texture
+            .texels()
+            .iter()
+            .map(|&texel| luminance(texel).max(0.0))
+            .collect();
+        let mut columns = Vec::with_capacity(height);
+        let mut row_weights = Vec::with_capacity(height);
+        for y in 0..height {
+            // rows near the poles are squeezed into a smaller solid angle
+            let sin_theta = ((y as Real + 0.5) / height as Real * PI).sin();
+            // bilinear filtering bleeds every texel into its neighbours
+            let weights = (0..width)
+                .map(|x| {
+                    let neighbours = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)));
+                    let brightest = neighbours
+                        .map(|(dx, dy)| {
+                            let x = (x as i64 + dx).rem_euclid(width as i64) as usize;
+                            let y = (y as i64 + dy).rem_euclid(height as i64) as usize;
+                            luminances[y * width + x]
+                        })
+                        .fold(0.0, Real::max);
+                    brightest * sin_theta
+                })
+                .collect();
+            let row = Distribution::new(weights);
+            row_weights.push(row.total);
+            columns.push(row);
+        }
+
+        Ok(Self {
+            texture,
+            width,
+            height,
+            texels_hash,
+            rows: Distribution::new(row_weights),
+            columns,
+            rotation: 0.0,
+            to_world: Quat::IDENTITY,
+            intensity: 1.0,
+        })
+    }
+
+    /// Without a `color_space`, float images (HDR, EXR) are read as linear and the others as
+    /// sRGB, like [`ImageTexture::from_path`]
+    pub fn from_path(path: impl AsRef<Path>, color_space: Option<ColorSpace>) -> ImageResult<Self> {
+        let image = image::open(path)?;
+        let color_space = color_space.unwrap_or_else(|| ColorSpace::of(&image));
+        Self::new(image.to_rgb32f(), color_space)
+    }
+
+    /// Turns the map by `degrees` around the y axis
+    pub fn rotation(mut self, degrees: Real) -> Self {
+        self.rotation = degrees;
+        self.to_world = Quat::from_rotation_y(degrees.to_radians());
+        self
+    }
+
+    /// Scales the radiance of the map
+    pub fn intensity(mut self, intensity: Real) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    fn uv(&self, direction: Vec3) -> (Real, Real) {
+        Sphere::get_uv(self.to_world.inverse() * direction.normalize())
+    }
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("rotation", &self.rotation)
+            .field("intensity", &self.intensity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let (u, v) = self.uv(direction);
+        self.intensity * self.texture.value(u, v, direction)
+    }
+
+    fn importance_sampled(&self) -> bool {
+        self.rows.total > 0.0
+    }
+
+    fn pdf_value(&self, direction: Vec3) -> Real {
+        let (u, v) = self.uv(direction);
+        let sin_theta = (v * PI).sin();
+        if !self.importance_sampled() || sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // v grows downwards in the image
+        let y = (((1.0 - v) * self.height as Real) as usize).min(self.height - 1);
+        let x = ((u * self.width as Real) as usize).min(self.width - 1);
+        let row = &self.columns[y];
+        if row.total <= 0.0 {
+            return 0.0;
+        }
+        let density = self.rows.density(y) * row.density(x);
+        // from (u, v) in the unit square to the 2 pi^2 sin(theta) larger solid angle
+        density / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn random(&self, rng: &mut dyn RngCore) -> Vec3 {
+        let (image_v, y) = self.rows.sample(rng.gen());
+        let (u, _) = self.columns[y].sample(rng.gen());
+        self.to_world * Sphere::uv_point(u, 1.0 - image_v)
+    }
+
+    fn hash_settings(&self, mut state: &mut dyn Hasher) {
+        (self.width, self.height, self.texels_hash).hash(&mut state);
+        [self.rotation, self.intensity]
+            .map(Real::to_bits)
+            .hash(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Rgb;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use crate::utils::random_in_unit_sphere;
+
+    use super::*;
+
+    #[test]
+    fn test_environment_pdf() {
+        // a dark map with one bright texel
+        let image = Rgb32FImage::from_fn(16, 8, |x, y| {
+            let value = if (x, y) == (3, 2) { 100.0 } else { 0.5 };
+            Rgb([value; 3])
+        });
+        let map = EnvironmentMap::new(image, ColorSpace::Linear)
+            .unwrap()
+            .rotation(90.0)
+            .intensity(2.0);
+        let rng = &mut SmallRng::seed_from_u64(0);
+
+        // the density integrates to 1 over the sphere
+        let n = 100000;
+        let integral = (0..n)
+            .map(|_| map.pdf_value(random_in_unit_sphere(rng)))
+            .sum::<Real>()
+            * 4.0
+            * PI
+            / n as Real;
+        assert!((integral - 1.0).abs() < 0.02);
+
+        // most samples go towards the bright texel, wherever the map is turned
+        let sun = map.to_world * Sphere::uv_point(3.5 / 16.0, 1.0 - 2.5 / 8.0);
+        assert!(map.color(sun).x > 100.0);
+        let towards_sun = (0..1000)
+            .filter(|_| map.random(rng).normalize().dot(sun) > 0.8)
+            .count();
+        assert!(towards_sun > 800);
+    }
+
+    #[test]
+    fn test_environment_hash() {
+        let hash = |map: &EnvironmentMap| {
+            let mut hasher = FnvHasher::default();
+            map.hash_settings(&mut hasher);
+            hasher.finish()
+        };
+        let map = |value: f32| {
+            let image = Rgb32FImage::from_pixel(4, 2, Rgb([value; 3]));
+            EnvironmentMap::new(image, ColorSpace::Linear).unwrap()
+        };
+
+        assert_eq!(hash(&map(1.0)), hash(&map(1.0)));
+        // another image of the same size
+        assert_ne!(hash(&map(1.0)), hash(&map(2.0)));
+        assert_ne!(hash(&map(1.0)), hash(&map(1.0).rotation(90.0)));
+    }
+
+    #[test]
+    fn test_environment_color_space() {
+        let dir = std::env::temp_dir().join(format!(
+            "raytracing-test-environment-color-space-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image = Rgb32FImage::from_pixel(4, 2, Rgb([0.5; 3]));
+        let gray = |name: &str, color_space| {
+            let map = EnvironmentMap::from_path(dir.join(name), color_space).unwrap();
+            map.color(Vec3::X).x
+        };
+        image::DynamicImage::from(image.clone())
+            .to_rgb8()
+            .save(dir.join("gray.png"))
+            .unwrap();
+        image.save(dir.join("gray.hdr")).unwrap();
+
+        // lights the scene as bright as the same image looks as a texture
+        assert!((gray("gray.png", None) - 0.214).abs() < 1e-2);
+        assert!((gray("gray.png", Some(ColorSpace::Linear)) - 0.5).abs() < 1e-2);
+        assert!((gray("gray.hdr", None) - 0.5).abs() < 1e-2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_distribution() {
+        let distribution = Distribution::new(vec![1.0, 0.0, 3.0]);
+        assert_eq!(distribution.sample(0.0), (0.0, 0));
+        assert_eq!(distribution.sample(0.25).1, 2);
+        assert!((distribution.sample(0.625).0 - 2.5 / 3.0).abs() < 1e-6);
+        assert!((distribution.density(2) - 2.25).abs() < 1e-6);
+        assert_eq!(distribution.density(1), 0.0);
+    }
+}
@@ -1,244 +1,694 @@
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::{path::Path, time::Instant};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
 
 use crate::{
+    adaptive::{AdaptiveSampling, PixelStats, SampleCounts},
+    background::{BackgroundRef, Gradient},
     log::logger,
-    Hittable,
-    utils::{linear_to_gamma, random_in_unit_disk},
-    Ray,
+    material::ScatterRecord,
+    math::{to_f32_array, Real, Vec3},
+    output,
+    pdf::{BackgroundPdf, HittablePdf, MixturePdf, Pdf},
+    progressive::Accumulator,
+    tonemap::{ToneMapper, ToneMapping},
+    utils::sample_rng,
+    Hittable, MaterialRef, Ray, Sampleable,
 };
 use ::log::info;
-use glam::Vec3;
-use image::{ImageBuffer, Rgb};
+use image::{Rgb, Rgb32FImage};
 use indicatif::ProgressBar;
-use rand::random;
+use rand::{Rng, RngCore};
+use rt_core::camera::{Camera as BaseCamera, Viewport};
 
-pub fn ray_color<W: Hittable>(ray: &Ray, world: &W, depth: u32) -> Vec3 {
-    if depth <= 0 {
-        return Vec3::ZERO;
-    }
+pub struct Camera {
+    /// Where the rays go, how many of them and how deep they are traced
+    base: BaseCamera,
 
-    let unit_direction = ray.direction.normalize();
+    /// What rays that escape the scene see
+    background: BackgroundRef,
 
-    // use 0.001 to avoid shadow acne
-    if let Some(record) = world.hit(ray, 0.001..f32::INFINITY) {
-        if let Some(material) = &record.material {
-            return material
-                .scatter(ray, &record)
-                .map(|(attenuation, scattered_ray)| {
-                    attenuation * ray_color(&scattered_ray, world, depth - 1)
-                })
-                .unwrap_or(Vec3::ZERO);
-        }
-    }
+    /// Rays are cast at random times within `[shutter_open, shutter_close)`
+    shutter_open: Real,
+    shutter_close: Real,
 
-    let a = 0.5 * (unit_direction.y + 1.0); // 从 [-1, 1] 映射到 [0, 1]
-    (1.0 - a) * Vec3::new(1.0, 1.0, 1.0) + a * Vec3::new(0.5, 0.7, 1.0)
-}
+    /// Objects that half of the diffuse bounces are sent towards
+    lights: Option<Arc<Box<dyn Sampleable + Send + Sync>>>,
 
-pub struct Camera {
-    // focal_length: f32,
-    fov: f32,
-    aspect_ratio: f32,
-
-    pos: Vec3,
-    look_at: Vec3,
-    up: Vec3,
-
-    samples_per_pixel: u32,
-    max_depth: u32,
-    defocus_angle: f32,
-    focus_distance: f32,
+    /// How the radiance is turned into colors for 8-bit outputs
+    tone_mapping: ToneMapping,
+
+    /// Seeds the random numbers of every sample, see [`sample_rng`]
+    seed: u64,
+
+    /// Lets pixels stop before `samples_per_pixel` once they have converged
+    adaptive: Option<AdaptiveSampling>,
 }
 
 impl Default for Camera {
     fn default() -> Self {
-        let aspect_ratio = 16.0 / 9.0;
-        // let focal_length = 1.0;
-        let fov = 90.0f32;
-
-        let pos = Vec3::ZERO;
-        let look_at = Vec3::NEG_Z;
-        let up = Vec3::Y;
-
-        let defocus_angle = 0.0;
-        let focus_distance = 10.0;
-
         Self {
-            aspect_ratio,
-            // focal_length,
-            fov,
-            pos,
-            look_at,
-            up,
-            defocus_angle,
-            focus_distance,
-
-            samples_per_pixel: 100,
-            max_depth: 50,
+            base: BaseCamera::default(),
+            background: Arc::new(Box::new(Gradient::default())),
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            lights: None,
+            tone_mapping: ToneMapping::default(),
+            seed: 0,
+            adaptive: None,
         }
     }
 }
 
 impl Camera {
-    pub fn new(aspect_ratio: f32) -> Self {
+    pub fn new(aspect_ratio: Real) -> Self {
         Camera {
-            aspect_ratio,
+            base: BaseCamera::new(aspect_ratio),
             ..Default::default()
         }
     }
 
+    pub fn aspect_ratio(mut self, aspect_ratio: Real) -> Self {
+        self.base = self.base.aspect_ratio(aspect_ratio);
+        self
+    }
+
     pub fn pos(mut self, pos: Vec3) -> Self {
-        self.pos = pos;
+        self.base = self.base.pos(pos);
         self
     }
 
     pub fn look_at(mut self, look_at: Vec3) -> Self {
-        self.look_at = look_at;
+        self.base = self.base.look_at(look_at);
         self
     }
 
     pub fn up(mut self, up: Vec3) -> Self {
-        self.up = up.normalize();
+        self.base = self.base.up(up);
         self
     }
 
-    pub fn defocus_angle(mut self, defocus_angle: f32) -> Self {
-        self.defocus_angle = defocus_angle;
+    pub fn defocus_angle(mut self, defocus_angle: Real) -> Self {
+        self.base = self.base.defocus_angle(defocus_angle);
         self
     }
 
-    pub fn focus_distance(mut self, focus_distance: f32) -> Self {
-        self.focus_distance = focus_distance;
+    pub fn focus_distance(mut self, focus_distance: Real) -> Self {
+        self.base = self.base.focus_distance(focus_distance);
         self
     }
 
     pub fn focus_to(mut self, target: Vec3) -> Self {
-        self.look_at = target;
-        self.focus_distance = self.pos.distance(target);
+        self.base = self.base.focus_to(target);
         self
     }
 
     pub fn samples_per_pixel(mut self, samples_per_pixel: u32) -> Self {
-        self.samples_per_pixel = samples_per_pixel;
+        self.base = self.base.samples_per_pixel(samples_per_pixel);
         self
     }
 
     pub fn max_depth(mut self, max_depth: u32) -> Self {
-        self.max_depth = max_depth;
+        self.base = self.base.max_depth(max_depth);
         self
     }
 
-    pub fn fov(mut self, fov: f32) -> Self {
-        self.fov = fov;
+    pub fn fov(mut self, fov: Real) -> Self {
+        self.base = self.base.fov(fov);
         self
     }
 
-    pub fn set_defocus_angle(&mut self, defocus_angle: f32) -> &mut Self {
-        self.defocus_angle = defocus_angle;
+    pub fn background(mut self, background: BackgroundRef) -> Self {
+        self.background = background;
         self
     }
 
-    pub fn set_focus_distance(&mut self, focus_distance: f32) -> &mut Self {
-        self.focus_distance = focus_distance;
+    pub fn shutter(mut self, open: Real, close: Real) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    /// Registers the light sources for explicit light sampling, use
+    /// [`Lights`](crate::world::list::Lights) for several lights
+    pub fn lights(mut self, lights: Arc<Box<dyn Sampleable + Send + Sync>>) -> Self {
+        self.lights = Some(lights);
+        self
+    }
+
+    pub fn tone_mapper(mut self, tone_mapper: ToneMapper) -> Self {
+        self.tone_mapping.tone_mapper = tone_mapper;
+        self
+    }
+
+    /// Exposure value applied before tone mapping, every +1 doubles the brightness
+    pub fn exposure(mut self, exposure: Real) -> Self {
+        self.tone_mapping.exposure = exposure;
+        self
+    }
+
+    /// The same seed renders the same image, no matter how many threads are used
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Samples every pixel only until it converges, `samples_per_pixel` becomes the maximum.
+    /// Not used by [`Camera::render_progressive`].
+    pub fn adaptive(mut self, adaptive: AdaptiveSampling) -> Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    pub fn set_defocus_angle(&mut self, defocus_angle: Real) -> &mut Self {
+        self.base.set_defocus_angle(defocus_angle);
+        self
+    }
+
+    pub fn set_focus_distance(&mut self, focus_distance: Real) -> &mut Self {
+        self.base.set_focus_distance(focus_distance);
         self
     }
 
     pub fn set_look_at(&mut self, look_at: Vec3) -> &mut Self {
-        self.look_at = look_at;
+        self.base.set_look_at(look_at);
         self
     }
 
     pub fn set_up(&mut self, up: Vec3) -> &mut Self {
-        self.up = up.normalize();
+        self.base.set_up(up);
+        self
+    }
+
+    pub fn set_background(&mut self, background: BackgroundRef) -> &mut Self {
+        self.background = background;
         self
     }
 
     pub fn focus_point(&self) -> Vec3 {
-        self.pos + self.focus_distance * (self.look_at - self.pos).normalize()
+        self.base.focus_point()
+    }
+
+    pub fn output_height(&self, output_width: u32) -> u32 {
+        self.base.output_height(output_width)
+    }
+
+    pub fn tone_mapping(&self) -> &ToneMapping {
+        &self.tone_mapping
+    }
+
+    pub fn adaptive_sampling(&self) -> Option<AdaptiveSampling> {
+        self.adaptive
+    }
+
+    /// Feeds every setting that changes the rendered radiance into `state`, to tell whether a
+    /// [checkpoint](Accumulator) belongs to this camera. The samples per pixel and the tone
+    /// mapping are left out, so they can change between runs.
+    pub fn hash_settings<H: Hasher>(&self, state: &mut H) {
+        self.base.hash_settings(state);
+        [self.shutter_open, self.shutter_close]
+            .map(Real::to_bits)
+            .hash(state);
+        self.seed.hash(state);
+        self.background.hash_settings(state);
+        self.lights.is_some().hash(state);
+        if let Some(lights) = &self.lights {
+            lights.hash_settings(state);
+        }
+    }
+}
+
+impl fmt::Display for Camera {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.base)?;
+        writeln!(
+            f,
+            "background: {:?}, light sampling: {}",
+            self.background,
+            self.lights.is_some()
+        )?;
+        writeln!(
+            f,
+            "tone mapper: {:?}, exposure: {}, seed: {}",
+            self.tone_mapping.tone_mapper, self.tone_mapping.exposure, self.seed
+        )?;
+        match &self.adaptive {
+            Some(adaptive) => write!(
+                f,
+                "adaptive sampling: min samples: {}, threshold: {}",
+                adaptive.min_samples, adaptive.threshold
+            ),
+            None => write!(f, "adaptive sampling: off"),
+        }
     }
 }
 
 impl Camera {
-    pub fn render_to_path<W: Hittable + Send + Sync>(
+    pub fn ray_color<W: Hittable<MaterialRef>>(
         &self,
+        ray: &Ray,
         world: &W,
-        output_width: u32,
-        path: impl AsRef<Path>,
-    ) {
-        let back = (self.pos - self.look_at).normalize();
-        let right = self.up.cross(back).normalize();
-        let up = back.cross(right).normalize();
+        depth: u32,
+        rng: &mut dyn RngCore,
+    ) -> Vec3 {
+        if depth == 0 {
+            return Vec3::ZERO;
+        }
 
-        let h = self.focus_distance * (self.fov / 2.0).to_radians().tan();
-        let viewport_height = 2.0 * h;
-        let viewport_width = viewport_height * self.aspect_ratio;
+        // use 0.001 to avoid shadow acne
+        let Some(record) = world.hit(ray, 0.001..Real::INFINITY) else {
+            return self.background.color(ray.direction);
+        };
+        let Some(material) = &record.material else {
+            return self.background.color(ray.direction);
+        };
 
-        let viewport_u = viewport_width * right;
-        let viewport_v = -viewport_height * up;
+        let emitted = material.emitted(record.u, record.v, record.point);
+        let Some(scatter) = material.scatter(ray, &record, rng) else {
+            return emitted;
+        };
 
-        let output_height = (output_width as f32 / self.aspect_ratio) as u32;
-        let pixel_delta_u = viewport_u / output_width as f32;
-        let pixel_delta_v = viewport_v / output_height as f32;
+        match scatter {
+            ScatterRecord::Specular {
+                attenuation,
+                ray: scattered_ray,
+            } => emitted + attenuation * self.ray_color(&scattered_ray, world, depth - 1, rng),
+            ScatterRecord::Pdf { attenuation, pdf } => {
+                let light_pdf = self.lights.as_ref().map(|lights| {
+                    HittablePdf::new(lights.as_ref().as_ref(), record.point, ray.time)
+                });
+                let background_pdf = self
+                    .background
+                    .importance_sampled()
+                    .then(|| BackgroundPdf::new(self.background.as_ref().as_ref()));
+
+                // half of the rays go towards the lights and the background, if there are any
+                let (both, mixture);
+                let emitters: Option<&dyn Pdf> = match (&light_pdf, &background_pdf) {
+                    (Some(lights), Some(background)) => {
+                        both = MixturePdf::new(lights, background);
+                        Some(&both)
+                    }
+                    (Some(lights), None) => Some(lights),
+                    (None, Some(background)) => Some(background),
+                    (None, None) => None,
+                };
+                let pdf: &dyn Pdf = match emitters {
+                    Some(emitters) => {
+                        mixture = MixturePdf::new(emitters, pdf.as_ref());
+                        &mixture
+                    }
+                    None => pdf.as_ref(),
+                };
+                let direction = pdf.generate(rng);
+                let pdf_value = pdf.value(direction);
+                if pdf_value <= Real::EPSILON {
+                    return emitted;
+                }
+
+                let scattered_ray = Ray::new(record.point, direction)
+                    .with_time(ray.time)
+                    .with_channel(ray.channel);
+                let scattering = material.scattering(ray, &record, &scattered_ray);
+                let color = self.ray_color(&scattered_ray, world, depth - 1, rng);
+                emitted + attenuation * scattering * color / pdf_value
+            }
+        }
+    }
+}
 
-        let defocus_radius = self.focus_distance * (self.defocus_angle / 2.0).to_radians().tan();
-        println!("defocus_radius: {}", defocus_radius);
-        let defocus_disk_u = defocus_radius * right;
-        let defocus_disk_v = -defocus_radius * up;
+impl Camera {
+    pub fn render_to_path<W: Hittable<MaterialRef> + Send + Sync>(
+        &self,
+        world: &W,
+        output_width: u32,
+        path: impl AsRef<Path>,
+    ) {
+        // 防止等半天渲染完了才写入失败，要是失败干脆就先失败，现在不失败后面应该也不失败（有点蠢的方法）
+        output::check_writable(&path).unwrap();
 
-        let viewport_upper_left =
-            self.pos - self.focus_distance * back - viewport_u / 2.0 - viewport_v / 2.0;
-        let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+        let image = self.render(world, output_width);
+        output::save(&image, &path, &self.tone_mapping).unwrap();
+    }
 
-        let calc_pixel_color = |x: u32, y: u32| {
-            let pixel_center =
-                pixel00_loc + (x as f32 * pixel_delta_u) + (y as f32 * pixel_delta_v);
-
-            let color = (0..self.samples_per_pixel)
-                .into_par_iter()
-                .map(|_| {
-                    let rand_offset = Vec3::new(
-                        (random::<f32>() - 0.5) * pixel_delta_u.x,
-                        (random::<f32>() - 0.5) * pixel_delta_v.y,
-                        0.0,
-                    );
-
-                    let ray_origin = if self.defocus_angle <= f32::EPSILON {
-                        self.pos
-                    } else {
-                        let rand_vec = random_in_unit_disk();
-                        self.pos + (defocus_disk_u * rand_vec.x) + (defocus_disk_v * rand_vec.y)
-                    };
-                    let ray = Ray::new(ray_origin, pixel_center + rand_offset - ray_origin);
-
-                    ray_color(&ray, world, self.max_depth)
-                })
-                .sum::<Vec3>()
-                / self.samples_per_pixel as f32;
+    /// Renders the linear radiance of every pixel, see [`output::save`] for saving it
+    pub fn render<W: Hittable<MaterialRef> + Send + Sync>(
+        &self,
+        world: &W,
+        output_width: u32,
+    ) -> Rgb32FImage {
+        self.render_with_sample_counts(world, output_width).0
+    }
 
-            let color = linear_to_gamma(color);
+    /// [`Camera::render`], also returning how many samples every pixel took, which only
+    /// differ with [adaptive sampling](Camera::adaptive)
+    pub fn render_with_sample_counts<W: Hittable<MaterialRef> + Send + Sync>(
+        &self,
+        world: &W,
+        output_width: u32,
+    ) -> (Rgb32FImage, SampleCounts) {
+        let output_height = self.output_height(output_width);
+        let viewport = self.base.viewport(output_width);
 
-            let color_u8 = (255.999 * color).clamp(Vec3::ZERO, Vec3::splat(255.0));
-            Rgb([color_u8.x as u8, color_u8.y as u8, color_u8.z as u8])
+        // every pixel sums its samples in order, so the result does not depend on scheduling
+        let calc_pixel_color = |x: u32, y: u32| {
+            let mut stats = PixelStats::default();
+            for sample in 0..self.base.sample_count() {
+                stats.add(self.sample_pixel(&viewport, world, x, y, sample));
+                if self
+                    .adaptive
+                    .is_some_and(|adaptive| adaptive.converged(&stats))
+                {
+                    break;
+                }
+            }
+            (stats.mean(), stats.samples())
         };
 
-        let mut image: ImageBuffer<Rgb<u8>, Vec<u8>> =
-            ImageBuffer::new(output_width, output_height);
-        // 防止等半天渲染完了才写入失败，要是失败干脆就先失败，现在不失败后面应该也不失败（有点蠢的方法）
-        image.save(&path).unwrap();
-
         let t = Instant::now();
         info!("generating image...");
         let multi = logger().multi();
         let pb = multi.add(ProgressBar::new((output_height * output_width) as u64));
-        image.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
-            *pixel = calc_pixel_color(x, y);
+        let tiles = Tile::split(output_width, output_height)
+            .into_par_iter()
+            .map(|tile| {
+                let colors = tile
+                    .pixels()
+                    .map(|(x, y)| calc_pixel_color(x, y))
+                    .collect::<Vec<_>>();
+                pb.inc(colors.len() as u64);
+                (tile, colors)
+            })
+            .collect::<Vec<_>>();
+        pb.finish();
+        multi.remove(&pb);
+
+        let mut image = Rgb32FImage::new(output_width, output_height);
+        let mut counts = SampleCounts::new(output_width, output_height, self.base.sample_count());
+        for (tile, colors) in tiles {
+            for ((x, y), (color, samples)) in tile.pixels().zip(colors) {
+                image.put_pixel(x, y, Rgb(to_f32_array(color)));
+                counts.set(x, y, samples);
+            }
+        }
+
+        info!("cost: {:?}", t.elapsed());
+        if self.adaptive.is_some() {
+            info!("average samples per pixel: {}", counts.mean());
+        }
+        (image, counts)
+    }
+
+    /// Adds passes of `samples_per_pass` samples to every pixel of `accumulator` until it
+    /// holds [`Camera::samples_per_pixel`] samples, `on_pass` is called after every pass
+    pub fn render_progressive<W, F>(
+        &self,
+        world: &W,
+        accumulator: &mut Accumulator,
+        samples_per_pass: u32,
+        mut on_pass: F,
+    ) where
+        W: Hittable<MaterialRef> + Send + Sync,
+        F: FnMut(&Accumulator),
+    {
+        let output_width = accumulator.width();
+        assert_eq!(accumulator.height(), self.output_height(output_width));
+        let viewport = self.base.viewport(output_width);
+
+        let remaining = self
+            .base
+            .sample_count()
+            .saturating_sub(accumulator.samples());
+        let passes = remaining.div_ceil(samples_per_pass.max(1));
+
+        let t = Instant::now();
+        info!(
+            "generating image in {passes} passes, resuming from {} samples...",
+            accumulator.samples()
+        );
+        let multi = logger().multi();
+        let pb = multi.add(ProgressBar::new(passes as u64));
+        while accumulator.samples() < self.base.sample_count() {
+            let samples = samples_per_pass
+                .max(1)
+                .min(self.base.sample_count() - accumulator.samples());
+            let first_sample = accumulator.samples();
+            accumulator.add_pass(samples, |x, y| {
+                (first_sample..first_sample + samples)
+                    .map(|sample| self.sample_pixel(&viewport, world, x, y, sample))
+                    .sum()
+            });
             pb.inc(1);
-        });
+            on_pass(accumulator);
+        }
         pb.finish();
         multi.remove(&pb);
 
-        image.save(&path).unwrap();
         info!("cost: {:?}", t.elapsed());
     }
+
+    /// The radiance along one random ray through pixel `(x, y)`, the random numbers of each
+    /// `sample` are fixed by the camera's seed
+    fn sample_pixel<W: Hittable<MaterialRef>>(
+        &self,
+        viewport: &Viewport,
+        world: &W,
+        x: u32,
+        y: u32,
+        sample: u32,
+    ) -> Vec3 {
+        let rng = &mut sample_rng(self.seed, x, y, sample);
+        let ray = viewport.ray(x, y, rng);
+        let ray_time =
+            self.shutter_open + rng.gen::<Real>() * (self.shutter_close - self.shutter_open);
+        let ray = ray.with_time(ray_time);
+
+        self.ray_color(&ray, world, self.base.depth_limit(), rng)
+    }
+}
+
+/// A block of pixels rendered by one task
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Tile {
+    const SIZE: u32 = 16;
+
+    /// Covers the image with tiles of at most `SIZE` x `SIZE` pixels, row by row
+    fn split(width: u32, height: u32) -> Vec<Tile> {
+        (0..height)
+            .step_by(Self::SIZE as usize)
+            .flat_map(|y| {
+                (0..width).step_by(Self::SIZE as usize).map(move |x| Tile {
+                    x,
+                    y,
+                    width: Self::SIZE.min(width - x),
+                    height: Self::SIZE.min(height - y),
+                })
+            })
+            .collect()
+    }
+
+    fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let Tile {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+        (y..y + height).flat_map(move |y| (x..x + width).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::SmallRng, SeedableRng};
+    use rayon::ThreadPoolBuilder;
+
+    use crate::{
+        background::{Background, Color, EnvironmentMap},
+        material::{DiffuseLight, Lambertian},
+        math::consts::PI,
+        primitive::Quad,
+        scene::{builtin, Scene},
+        texture::{ColorSpace, SolidColor},
+        world::list::List,
+    };
+
+    use super::*;
+
+    /// mean and variance of the radiance estimates for a ray hitting a floor lit by a small light
+    fn estimate(camera: &Camera, world: &List, samples: usize) -> (Real, Real) {
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::NEG_Y);
+        let rng = &mut SmallRng::seed_from_u64(0);
+        let values = (0..samples)
+            .map(|_| camera.ray_color(&ray, world, 2, rng).x)
+            .collect::<Vec<_>>();
+        let mean = values.iter().sum::<Real>() / samples as Real;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<Real>() / samples as Real;
+        (mean, variance)
+    }
+
+    #[test]
+    fn test_light_sampling_reduces_variance() {
+        let light_quad = || {
+            Quad::new(
+                Vec3::new(-0.5, 5.0, -0.5),
+                Vec3::X,
+                Vec3::Z,
+                Arc::new(Box::new(DiffuseLight::new(Arc::new(Box::new(
+                    SolidColor::new(Vec3::splat(50.0)),
+                ))))),
+            )
+        };
+        let world = List::from_objects(vec![
+            Box::new(Quad::new(
+                Vec3::new(-50.0, 0.0, -50.0),
+                Vec3::new(100.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 100.0),
+                Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                    SolidColor::new(Vec3::ONE),
+                ))))),
+            )),
+            Box::new(light_quad()),
+        ]);
+
+        let camera = Camera::default().background(Arc::new(Box::new(Color(Vec3::ZERO))));
+        let (mean, variance) = estimate(&camera, &world, 20000);
+
+        let camera = camera.lights(Arc::new(Box::new(light_quad())));
+        let (sampled_mean, sampled_variance) = estimate(&camera, &world, 20000);
+
+        // irradiance from a small light straight above: E / PI * A * cos^2 / d^2
+        let expected = 50.0 / PI / 25.0;
+        assert!((sampled_mean - expected).abs() < 0.05 * expected);
+        assert!((mean - expected).abs() < 0.5 * expected);
+        assert!(sampled_variance * 10.0 < variance);
+    }
+
+    #[test]
+    fn test_environment_sampling_reduces_variance() {
+        /// The same map, only never sampled
+        #[derive(Debug)]
+        struct Unsampled(EnvironmentMap);
+
+        impl Background for Unsampled {
+            fn color(&self, direction: Vec3) -> Vec3 {
+                self.0.color(direction)
+            }
+
+            fn hash_settings(&self, state: &mut dyn Hasher) {
+                self.0.hash_settings(state);
+            }
+        }
+
+        // a dim sky with a small and bright sun high above the floor
+        let image = Rgb32FImage::from_fn(32, 16, |x, y| {
+            let value = if (x, y) == (5, 3) { 500.0 } else { 0.1 };
+            Rgb([value; 3])
+        });
+        let sky = || {
+            EnvironmentMap::new(image.clone(), ColorSpace::Linear)
+                .unwrap()
+                .rotation(30.0)
+        };
+        let world = List::from_objects(vec![Box::new(Quad::new(
+            Vec3::new(-50.0, 0.0, -50.0),
+            Vec3::new(100.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::ONE),
+            ))))),
+        ))]);
+
+        let camera = Camera::default().background(Arc::new(Box::new(Unsampled(sky()))));
+        let (mean, variance) = estimate(&camera, &world, 20000);
+
+        let camera = camera.background(Arc::new(Box::new(sky())));
+        let (sampled_mean, sampled_variance) = estimate(&camera, &world, 20000);
+
+        // the cosine weighted integral of the sky over the upper hemisphere, divided by PI
+        let (sky, n) = (sky(), 400);
+        let (d_theta, d_phi) = (PI / 2.0 / n as Real, 2.0 * PI / (2 * n) as Real);
+        let mut expected = 0.0;
+        for i in 0..n {
+            let theta = (i as Real + 0.5) * d_theta;
+            for j in 0..2 * n {
+                let phi = (j as Real + 0.5) * d_phi;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                let cos_solid_angle = theta.cos() * theta.sin() * d_theta * d_phi;
+                expected += sky.color(direction).x * cos_solid_angle;
+            }
+        }
+        let expected = expected / PI;
+
+        assert!((sampled_mean - expected).abs() < 0.05 * expected);
+        assert!((mean - expected).abs() < 0.5 * expected);
+        assert!(sampled_variance * 10.0 < variance);
+    }
+
+    #[test]
+    fn test_hash_settings() {
+        let hash = |camera: &Camera| {
+            let mut hasher = fnv::FnvHasher::default();
+            camera.hash_settings(&mut hasher);
+            hasher.finish()
+        };
+        let light = |y: Real| -> Arc<Box<dyn Sampleable + Send + Sync>> {
+            Arc::new(Box::new(Quad::new(
+                Vec3::new(-0.5, y, -0.5),
+                Vec3::X,
+                Vec3::Z,
+                Arc::new(Box::new(DiffuseLight::new(Arc::new(Box::new(
+                    SolidColor::new(Vec3::ONE),
+                ))))),
+            )))
+        };
+
+        let camera = Camera::default().lights(light(5.0));
+        assert_eq!(hash(&camera), hash(&Camera::default().lights(light(5.0))));
+        assert_ne!(hash(&camera), hash(&Camera::default().lights(light(4.0))));
+        assert_ne!(hash(&camera), hash(&Camera::default()));
+        // tone mapping only changes the outputs, not the samples
+        assert_eq!(hash(&camera), hash(&camera.exposure(1.0)));
+    }
+
+    #[test]
+    fn test_render_is_deterministic() {
+        let render = |build: fn() -> Scene, seed: u64, threads: usize| {
+            let Scene { camera, world, .. } = build();
+            let camera = camera.samples_per_pixel(4).seed(seed);
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let image = pool.install(|| camera.render(&world, 40));
+            image
+                .into_raw()
+                .into_iter()
+                .map(f32::to_bits)
+                .collect::<Vec<_>>()
+        };
+
+        // spheres of every material, and smoke with light sampling
+        for build in [builtin::world as fn() -> Scene, builtin::cornell_smoke] {
+            let image = render(build, 1, 1);
+            assert_eq!(image, render(build, 1, 4));
+            assert_ne!(image, render(build, 2, 4));
+        }
+    }
 }
@@ -1,43 +1,25 @@
 pub mod camera;
-pub mod log;
 pub mod material;
-pub mod utils;
 pub mod world;
 pub mod texture;
+pub mod perlin;
 pub mod primitive;
+pub mod obj;
+pub mod pdf;
+pub mod scene;
+pub mod output;
+pub mod tonemap;
+pub mod progressive;
+pub mod adaptive;
+pub mod background;
 
-use std::{ops::Range, sync::Arc};
+use std::sync::Arc;
 
-use glam::Vec3;
 use material::Material;
 
-#[derive(Debug)]
-pub struct Ray {
-    pub origin: Vec3,
-    pub direction: Vec3,
-}
+pub use rt_core::{log, math, utils, Hittable, Ray, Sampleable};
 
-impl Ray {
-    pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Ray { origin, direction }
-    }
+/// The material every object of this crate hits with
+pub type MaterialRef = Arc<Box<dyn Material + Send + Sync>>;
 
-    pub fn at(&self, t: f32) -> Vec3 {
-        self.origin + self.direction * t
-    }
-}
-
-pub struct HitRecord {
-    pub point: Vec3,
-    /// Unit normal vector
-    pub normal: Vec3,
-    pub t: f32,
-    pub front_face: bool,
-    pub material: Option<Arc<Box<dyn Material + Send + Sync>>>,
-    pub u: f32,
-    pub v: f32,
-}
-
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord>;
-}
\ No newline at end of file
+pub type HitRecord = rt_core::HitRecord<MaterialRef>;
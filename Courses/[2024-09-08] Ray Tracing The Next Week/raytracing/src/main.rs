@@ -1,200 +1,380 @@
-use std::sync::Arc;
+use std::{
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process,
+};
 
-use glam::Vec3;
-use rand::random;
+use clap::Parser;
+use fnv::FnvHasher;
+use image::Rgb32FImage;
 use raytracing::{
-    camera::Camera,
-    material::{Dielectric, Lambertian, Material, Metal},
-    primitive::{Quad, Sphere},
-    texture::{CheckerTexture, ImageTexture, SolidCheckerTexture, SolidColor, Texture},
-    world::bvh::{AabbHittable, BvhNode},
+    math::{Real, Vec3},
+    output,
+    progressive::Accumulator,
+    scene::{builtin, Scene},
+    tonemap::ToneMapper,
 };
 
-// Ideal aspect ratio
-const ASPECT_RATIO: f32 = 16.0 / 9.0;
-
-fn quads() -> impl AabbHittable + Send + Sync {
-    let mut objects = Vec::new();
-
-    objects.push(Box::new(Quad::new(
-        Vec3::new(-3.0, -2.0, 5.0),
-        Vec3::new(0.0, 0.0, -4.0),
-        Vec3::new(0.0, 4.0, 0.0),
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidColor::new(Vec3::new(1.0, 0.2, 0.2)),
-        ))))),
-    )));
-    objects.push(Box::new(Quad::new(
-        Vec3::new(-3.0, -2.0, 5.0),
-        Vec3::new(0.0, 0.0, -4.0),
-        Vec3::new(0.0, 4.0, 0.0),
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidColor::new(Vec3::new(1.0, 0.2, 0.2)),
-        ))))),
-    )));
-    objects.push(Box::new(Quad::new(
-        Vec3::new(-2.0, -2.0, 0.0),
-        Vec3::new(4.0, 0.0, 0.0),
-        Vec3::new(0.0, 4.0, 0.0),
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidColor::new(Vec3::new(0.2, 1.0, 0.2)),
-        ))))),
-    )));
-    objects.push(Box::new(Quad::new(
-        Vec3::new(3.0, -2.0, 1.0),
-        Vec3::new(0.0, 0.0, 4.0),
-        Vec3::new(0.0, 4.0, 0.0),
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidColor::new(Vec3::new(0.2, 0.2, 1.0)),
-        ))))),
-    )));
-    objects.push(Box::new(Quad::new(
-        Vec3::new(-2.0, 3.0, 1.0),
-        Vec3::new(4.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, 4.0),
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidColor::new(Vec3::new(1.0, 0.5, 0.0)),
-        ))))),
-    )));
-    objects.push(Box::new(Quad::new(
-        Vec3::new(-2.0, -3.0, 5.0),
-        Vec3::new(4.0, 0.0, 0.0),
-        Vec3::new(0.0, 0.0, -4.0),
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidColor::new(Vec3::new(0.2, 0.8, 0.8)),
-        ))))),
-    )));
-
-    let objects = objects
-        .into_iter()
-        .map(|obj| obj as Box<dyn AabbHittable + Send + Sync>)
-        .collect();
-    BvhNode::from_objects(objects)
+/// Renders one of the built-in scenes or a RON scene file
+///
+/// Every camera option overrides the scene's own setting.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// A built-in scene name (see --list) or the path of a `.ron` scene file
+    #[arg(default_value = "cornell-box")]
+    scene: String,
+
+    /// Image width in pixels, the height follows from the aspect ratio
+    #[arg(short, long)]
+    width: Option<u32>,
+    /// Aspect ratio as `16:9` or `1.78`
+    #[arg(short, long, value_parser = parse_aspect_ratio)]
+    aspect: Option<Real>,
+    /// Samples per pixel
+    #[arg(short, long)]
+    spp: Option<u32>,
+    /// Maximum number of bounces
+    #[arg(short = 'd', long)]
+    max_depth: Option<u32>,
+    /// Vertical field of view in degrees
+    #[arg(long)]
+    fov: Option<Real>,
+    /// Camera position as `x,y,z`
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    pos: Option<Vec3>,
+    /// Point the camera looks at as `x,y,z`
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    look_at: Option<Vec3>,
+    /// Defocus (aperture) angle in degrees, 0 disables depth of field
+    #[arg(long)]
+    defocus_angle: Option<Real>,
+    /// Distance from the camera to the plane in perfect focus
+    #[arg(long)]
+    focus_distance: Option<Real>,
+
+    /// Tone mapper for 8-bit outputs: `clamp`, `reinhard`, `reinhard-extended[:white]` or
+    /// `aces`
+    #[arg(short, long, value_parser = parse_tone_mapper)]
+    tone_mapper: Option<ToneMapper>,
+    /// Exposure value for 8-bit outputs, every +1 doubles the brightness
+    #[arg(short, long, allow_hyphen_values = true)]
+    exposure: Option<Real>,
+
+    /// Sample every pixel only until the 95% confidence interval of its luminance is within
+    /// this fraction of the mean, `--spp` becomes the maximum
+    #[arg(long)]
+    adaptive: Option<Real>,
+    /// Samples every pixel takes before adaptive sampling may stop it
+    #[arg(long)]
+    min_samples: Option<u32>,
+    /// Save how many samples every pixel took as a heatmap image
+    #[arg(long)]
+    heatmap: Option<PathBuf>,
+
+    /// Seed of the random numbers, the same seed always renders the same image
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Output image path, `.exr` and `.hdr` keep the linear radiance while other formats are
+    /// 8-bit, repeat it to save one render in several formats
+    #[arg(short, long, default_value = "image.png")]
+    output: Vec<PathBuf>,
+
+    /// Render in passes of this many samples per pixel, saving the outputs between passes
+    #[arg(long)]
+    pass_samples: Option<u32>,
+    /// Save the outputs (and the checkpoint) every this many passes
+    #[arg(long, default_value_t = 1)]
+    save_every: u32,
+    /// Checkpoint file of a progressive render, resumed from if it exists. Only the scene file
+    /// itself is checked, delete the checkpoint after editing the images or obj files it uses
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Print the scene statistics and exit without rendering
+    #[arg(long)]
+    stats: bool,
+    /// List the built-in scenes and exit
+    #[arg(long)]
+    list: bool,
 }
 
-fn world() -> impl AabbHittable + Send + Sync {
-    let mut objects = Vec::new();
-    objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidCheckerTexture::new(
-                0.5,
-                Arc::new(Box::new(SolidColor::new(Vec3::new(0.2, 0.3, 0.1)))),
-                Arc::new(Box::new(SolidColor::new(Vec3::new(0.9, 0.9, 0.9)))),
-            ),
-        ))))),
-    )));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let center = Vec3::new(
-                a as f32 + 0.9 * random::<f32>(),
-                0.2,
-                b as f32 + 0.9 * random::<f32>(),
-            );
+fn parse_aspect_ratio(s: &str) -> Result<Real, String> {
+    let aspect_ratio = match s.split_once([':', '/']) {
+        Some((w, h)) => {
+            let w = w.trim().parse::<Real>().map_err(|err| err.to_string())?;
+            let h = h.trim().parse::<Real>().map_err(|err| err.to_string())?;
+            w / h
+        }
+        None => s.trim().parse::<Real>().map_err(|err| err.to_string())?,
+    };
+    if aspect_ratio.is_finite() && aspect_ratio > 0.0 {
+        Ok(aspect_ratio)
+    } else {
+        Err(format!("invalid aspect ratio {s:?}"))
+    }
+}
 
-            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                let choose_mat = random::<f32>();
-
-                let albedo = Vec3::new(random::<f32>(), random::<f32>(), random::<f32>())
-                    * Vec3::new(random::<f32>(), random::<f32>(), random::<f32>());
-                let texture: Arc<Box<dyn Texture + Send + Sync>> =
-                    Arc::new(Box::new(SolidColor::new(albedo)));
-                let material: Box<dyn Material + Send + Sync> = if choose_mat < 0.8 {
-                    Box::new(Lambertian::new(texture))
-                } else if choose_mat < 0.95 {
-                    Box::new(Metal::new(texture).fuzz(random::<f32>() * 0.5))
-                } else {
-                    Box::new(Dielectric::new(1.5))
-                };
-
-                objects.push(Box::new(Sphere::new(center, 0.2, Arc::new(material))));
+fn parse_tone_mapper(s: &str) -> Result<ToneMapper, String> {
+    let (name, white) = match s.split_once(':') {
+        Some((name, white)) => (name, Some(white)),
+        None => (s, None),
+    };
+    match (name, white) {
+        ("clamp", None) => Ok(ToneMapper::Clamp),
+        ("reinhard", None) => Ok(ToneMapper::Reinhard),
+        ("reinhard-extended", white) => {
+            let white = match white {
+                Some(white) => white.parse::<Real>().map_err(|err| err.to_string())?,
+                None => 4.0,
+            };
+            let tone_mapper = ToneMapper::ReinhardExtended { white };
+            if tone_mapper.is_valid() {
+                Ok(tone_mapper)
+            } else {
+                Err(format!("white point must be positive, got {white}"))
             }
         }
+        ("aces", None) => Ok(ToneMapper::Aces),
+        _ => Err(format!("unknown tone mapper {s:?}")),
     }
-
-    objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        Arc::new(Box::new(Dielectric::new(1.5))),
-    )));
-    objects.push(Box::new(Sphere::new(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
-            SolidColor::new(Vec3::new(0.4, 0.2, 0.1)),
-        ))))),
-    )));
-    objects.push(Box::new(Sphere::new(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        Arc::new(Box::new(
-            Metal::new(Arc::new(Box::new(SolidColor::new(Vec3::new(
-                0.7, 0.6, 0.5,
-            )))))
-            .fuzz(0.0),
-        )),
-    )));
-
-    let objects = objects
-        .into_iter()
-        .map(|obj| obj as Box<dyn AabbHittable + Send + Sync>)
-        .collect();
-    BvhNode::from_objects(objects)
 }
 
-fn checkered_spheres() -> impl AabbHittable + Send + Sync {
-    let mut objects = Vec::new();
-
-    let earth_texture = ImageTexture::from_path("assets/earthmap.jpg");
-    let earth_texture: Arc<Box<dyn Texture + Send + Sync>> = Arc::new(Box::new(earth_texture));
-    let material: Arc<Box<dyn Material + Send + Sync>> =
-        Arc::new(Box::new(Lambertian::new(earth_texture)));
-
-    objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, -10.0, 0.0),
-        10.0,
-        material.clone(),
-    )));
-    objects.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 10.0, 0.0),
-        10.0,
-        material.clone(),
-    )));
-
-    let objects = objects
-        .into_iter()
-        .map(|obj| obj as Box<dyn AabbHittable + Send + Sync>)
-        .collect();
-    BvhNode::from_objects(objects)
+fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let components = s
+        .split(',')
+        .map(|x| x.trim().parse::<Real>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+    match components[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("expected `x,y,z`, got {s:?}")),
+    }
 }
 
 fn main() {
-    // Setup world
-
-    // Image
-    let image_width = 1280;
-    let image_height = (image_width as f32 / ASPECT_RATIO) as usize;
-    let aspect_ratio = image_width as f32 / image_height as f32; // real aspect ratio
-
-    let camera = Camera::new(aspect_ratio)
-        .samples_per_pixel(500)
-        .fov(80.0)
-        .pos(Vec3::new(0.0, 0.0, 9.0))
-        .look_at(Vec3::ZERO)
-        .focus_distance(10.0);
-
-    // i9-9900k: cost: 419.357666s
-    // let objects = objects
-    //     .into_iter()
-    //     .map(|obj| obj as Box<dyn Hittable + Send + Sync>)
-    //     .collect();
-    // let world = List::from_objects(objects);
-    // camera.render_to_path(&world, image_width, "image.png");
-
-    // i9-9900k: ramdom axis cost: 76.5858159s
-    // i9-9900k: longest axis cost: 69.4391338s
-    let world = quads();
-    camera.render_to_path(&world, image_width, "image.png");
+    let args = Args::parse();
+
+    if args.list {
+        for name in builtin::NAMES {
+            println!("{name}");
+        }
+        return;
+    }
+
+    // what the scene is built from, for checkpoints, with a hash that stays the same across
+    // Rust releases unlike `DefaultHasher`
+    let mut hasher = FnvHasher::default();
+    let Scene {
+        mut camera,
+        world,
+        mut width,
+    } = match builtin::by_name(&args.scene) {
+        Some(scene) => {
+            let scene = scene.unwrap_or_else(|err| {
+                eprintln!("error: {err}");
+                process::exit(1);
+            });
+            // built-in scenes are the same every run, their random parts are seeded
+            hasher.write(args.scene.as_bytes());
+            scene
+        }
+        None if Path::new(&args.scene).is_file() => {
+            let scene = Scene::from_path(&args.scene).unwrap_or_else(|err| {
+                eprintln!("error: {err}");
+                process::exit(1);
+            });
+            // a failed read is reported by from_path already, the assets it references are not
+            // hashed, see `--checkpoint`
+            hasher.write(&std::fs::read(&args.scene).unwrap());
+            scene
+        }
+        None => {
+            eprintln!(
+                "error: {:?} is neither a scene file nor a built-in scene ({})",
+                args.scene,
+                builtin::NAMES.join(", ")
+            );
+            process::exit(1);
+        }
+    };
+
+    if let Some(w) = args.width {
+        width = w;
+    }
+    if let Some(aspect_ratio) = args.aspect {
+        camera = camera.aspect_ratio(aspect_ratio);
+    }
+    if let Some(samples_per_pixel) = args.spp {
+        camera = camera.samples_per_pixel(samples_per_pixel);
+    }
+    if let Some(max_depth) = args.max_depth {
+        camera = camera.max_depth(max_depth);
+    }
+    if let Some(fov) = args.fov {
+        camera = camera.fov(fov);
+    }
+    if let Some(pos) = args.pos {
+        camera = camera.pos(pos);
+    }
+    if let Some(look_at) = args.look_at {
+        camera = camera.look_at(look_at);
+    }
+    if let Some(defocus_angle) = args.defocus_angle {
+        camera = camera.defocus_angle(defocus_angle);
+    }
+    if let Some(focus_distance) = args.focus_distance {
+        camera = camera.focus_distance(focus_distance);
+    }
+    if let Some(tone_mapper) = args.tone_mapper {
+        camera = camera.tone_mapper(tone_mapper);
+    }
+    if let Some(exposure) = args.exposure {
+        camera = camera.exposure(exposure);
+    }
+    if let Some(seed) = args.seed {
+        camera = camera.seed(seed);
+    }
+    if args.adaptive.is_some() || args.min_samples.is_some() {
+        let mut adaptive = camera.adaptive_sampling().unwrap_or_default();
+        if let Some(threshold) = args.adaptive {
+            adaptive.threshold = threshold;
+        }
+        if let Some(min_samples) = args.min_samples {
+            adaptive.min_samples = min_samples;
+        }
+        camera = camera.adaptive(adaptive);
+    }
+    let scene = Scene {
+        camera,
+        world,
+        width,
+    };
+
+    if args.stats {
+        println!("{}", scene.stats());
+        return;
+    }
+
+    // i9-9900k, `quads -w 1280 -a 16:9 -s 500 --fov 80`:
+    // - List: cost: 419.357666s
+    // - BvhNode with random axis: cost: 76.5858159s
+    // - BvhNode with longest axis: cost: 69.4391338s
+    // - Bvh (binned SAH, flattened) traces the rays of `world()` ~1.6x faster than BvhNode,
+    //   see `cargo bench --bench bvh`
+    let save = |image: &Rgb32FImage| {
+        for path in &args.output {
+            if let Err(err) = output::save(image, path, scene.camera.tone_mapping()) {
+                eprintln!("error: failed to save {path:?}: {err}");
+                process::exit(1);
+            }
+        }
+    };
+    // fail before rendering rather than after, without overwriting what a resumed render
+    // would show until its first pass
+    for path in &args.output {
+        if let Err(err) = output::check_writable(path) {
+            eprintln!("error: failed to save {path:?}: {err}");
+            process::exit(1);
+        }
+    }
+    let height = scene.camera.output_height(scene.width);
+
+    if args.pass_samples.is_none() && args.checkpoint.is_none() {
+        let (image, counts) = scene
+            .camera
+            .render_with_sample_counts(&scene.world, scene.width);
+        save(&image);
+        if let Some(path) = &args.heatmap {
+            if let Err(err) = counts.heatmap().save(path) {
+                eprintln!("error: failed to save {path:?}: {err}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if scene.camera.adaptive_sampling().is_some() || args.heatmap.is_some() {
+        eprintln!(
+            "error: adaptive sampling and heatmaps are not supported by progressive rendering"
+        );
+        process::exit(1);
+    }
+
+    scene.width.hash(&mut hasher);
+    scene.camera.hash_settings(&mut hasher);
+    let scene_hash = hasher.finish();
+
+    let mut accumulator = match &args.checkpoint {
+        Some(path) if path.exists() => Accumulator::resume(path, scene.width, height, scene_hash)
+            .unwrap_or_else(|err| {
+                eprintln!("error: failed to resume from {path:?}: {err}");
+                process::exit(1);
+            }),
+        _ => Accumulator::new(scene.width, height, scene_hash),
+    };
+    // the outputs show the samples so far, and the checkpoint lets a later run continue
+    let save_progress = |accumulator: &Accumulator| {
+        save(&accumulator.image());
+        if let Some(path) = &args.checkpoint {
+            if let Err(err) = accumulator.save(path) {
+                eprintln!("error: failed to save {path:?}: {err}");
+                process::exit(1);
+            }
+        }
+    };
+    let save_every = args.save_every.max(1);
+    scene.camera.render_progressive(
+        &scene.world,
+        &mut accumulator,
+        args.pass_samples.unwrap_or(16),
+        |accumulator| {
+            if accumulator.passes() % save_every == 0 {
+                save_progress(accumulator);
+            }
+        },
+    );
+    save_progress(&accumulator);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_aspect_ratio("16:9"), Ok(16.0 / 9.0));
+        assert_eq!(parse_aspect_ratio("1.5"), Ok(1.5));
+        assert!(parse_aspect_ratio("1:0").is_err());
+        assert_eq!(parse_vec3("1, -2,3.5"), Ok(Vec3::new(1.0, -2.0, 3.5)));
+        assert!(parse_vec3("1,2").is_err());
+        assert_eq!(
+            parse_tone_mapper("reinhard-extended:2"),
+            Ok(ToneMapper::ReinhardExtended { white: 2.0 })
+        );
+        assert_eq!(parse_tone_mapper("aces"), Ok(ToneMapper::Aces));
+        assert!(parse_tone_mapper("aces:2").is_err());
+        assert!(parse_tone_mapper("reinhard-extended:0").is_err());
+        assert!(parse_tone_mapper("reinhard-extended:-2").is_err());
+
+        let args = Args::parse_from([
+            "raytracing",
+            "quads",
+            "--pos",
+            "-1,0,9",
+            "-s",
+            "8",
+            "-e",
+            "-1.5",
+        ]);
+        assert_eq!(args.scene, "quads");
+        assert_eq!(args.pos, Some(Vec3::new(-1.0, 0.0, 9.0)));
+        assert_eq!(args.spp, Some(8));
+        assert_eq!(args.exposure, Some(-1.5));
+
+        let args = Args::parse_from(["raytracing", "--adaptive", "0.02", "--heatmap", "a.png"]);
+        assert_eq!(args.adaptive, Some(0.02));
+        assert_eq!(args.min_samples, None);
+        assert_eq!(args.heatmap, Some(PathBuf::from("a.png")));
+    }
 }
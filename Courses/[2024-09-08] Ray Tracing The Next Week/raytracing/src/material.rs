@@ -1,14 +1,50 @@
 use std::sync::Arc;
 
-use glam::Vec3;
-use rand::random;
+use rand::{Rng, RngCore};
+use serde::Deserialize;
 
 use crate::{
-    texture::Texture, utils::{random_in_unit_sphere, reflectance, refract}, HitRecord, Ray
+    math::{consts::PI, Real, Vec3},
+    pdf::{ggx_distribution, ggx_smith_g1, CosinePdf, MicrofacetPdf, Pdf, SpherePdf},
+    texture::Texture,
+    utils::{random_in_unit_sphere, reflectance, refract},
+    HitRecord, MaterialRef, Ray,
 };
 
+pub enum ScatterRecord {
+    /// Follows exactly one direction (mirror, glass), there is nothing to importance sample
+    Specular { attenuation: Vec3, ray: Ray },
+    /// Scatters following a pdf, which the renderer may mix with light sampling
+    Pdf {
+        attenuation: Vec3,
+        pdf: Box<dyn Pdf>,
+    },
+}
+
 pub trait Material {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)>;
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord>;
+
+    /// Density of the material scattering into `scattered`, only used for [`ScatterRecord::Pdf`]
+    fn scattering_pdf(&self, _ray: &Ray, _record: &HitRecord, _scattered: &Ray) -> Real {
+        0.0
+    }
+
+    /// The brdf times the cosine of `scattered` per channel, which weights the attenuation of
+    /// [`ScatterRecord::Pdf`]. Materials whose color doesn't depend on the direction only
+    /// need [`Material::scattering_pdf`].
+    fn scattering(&self, ray: &Ray, record: &HitRecord, scattered: &Ray) -> Vec3 {
+        Vec3::splat(self.scattering_pdf(ray, record, scattered))
+    }
+
+    /// Light emitted by the surface, non-emissive materials emit nothing
+    fn emitted(&self, _u: Real, _v: Real, _point: Vec3) -> Vec3 {
+        Vec3::ZERO
+    }
 }
 
 pub struct Lambertian {
@@ -22,21 +58,28 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)> {
-        let mut scatter_direction = record.normal + random_in_unit_sphere();
-        if scatter_direction.length_squared() <= f32::EPSILON {
-            scatter_direction = record.normal;
-        }
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        record: &HitRecord,
+        _rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        let attenuation = self.texture.value_at(record);
+        Some(ScatterRecord::Pdf {
+            attenuation,
+            pdf: Box::new(CosinePdf::new(record.normal)),
+        })
+    }
 
-        let scattered_ray = Ray::new(record.point, scatter_direction);
-        let attenuation = self.texture.value(record.u, record.v, record.point);
-        Some((attenuation, scattered_ray))
+    fn scattering_pdf(&self, _ray: &Ray, record: &HitRecord, scattered: &Ray) -> Real {
+        let cos_theta = record.normal.dot(scattered.direction.normalize());
+        (cos_theta / PI).max(0.0)
     }
 }
 
 pub struct Metal {
     texture: Arc<Box<dyn Texture + Send + Sync>>,
-    fuzz: f32,
+    fuzz: Real,
 }
 
 impl Metal {
@@ -47,66 +90,643 @@ impl Metal {
         }
     }
 
-    pub fn fuzz(mut self, fuzz: f32) -> Self {
+    pub fn fuzz(mut self, fuzz: Real) -> Self {
         self.fuzz = fuzz;
         self
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
         let reflected = ray.direction.reflect(record.normal);
-        let reflected = reflected.normalize() + self.fuzz * random_in_unit_sphere();
+        let reflected = reflected.normalize() + self.fuzz * random_in_unit_sphere(rng);
 
         if reflected.dot(record.normal) > 0.0 {
-            let scattered_ray = Ray::new(record.point, reflected);
-            let attenuation = self.texture.value(record.u, record.v, record.point);
-            Some((attenuation, scattered_ray))
+            let scattered_ray = Ray::new(record.point, reflected)
+                .with_time(ray.time)
+                .with_channel(ray.channel);
+            let attenuation = self.texture.value_at(record);
+            Some(ScatterRecord::Specular {
+                attenuation,
+                ray: scattered_ray,
+            })
         } else {
             None
         }
     }
 }
 
+/// The metallic-roughness material of glTF: GGX microfacets with Smith masking-shadowing and
+/// Schlick Fresnel, over a lambertian base that gets the light the microfacets don't reflect.
+/// Metals have no base, they tint the reflection instead.
+pub struct Principled {
+    base_color: Arc<Box<dyn Texture + Send + Sync>>,
+    /// Roughness in the green channel and metallic in the blue one, as glTF packs them
+    metallic_roughness: Option<Arc<Box<dyn Texture + Send + Sync>>>,
+    metallic: Real,
+    roughness: Real,
+}
+
+impl Principled {
+    /// Smooth microfacets are a delta distribution, which can't be sampled from a pdf
+    const MIN_ALPHA: Real = 1e-3;
+
+    /// A fully rough dielectric
+    pub fn new(base_color: Arc<Box<dyn Texture + Send + Sync>>) -> Self {
+        Principled {
+            base_color,
+            metallic_roughness: None,
+            metallic: 0.0,
+            roughness: 1.0,
+        }
+    }
+
+    /// Multiplies the blue channel of the `metallic_roughness` texture
+    pub fn metallic(mut self, metallic: Real) -> Self {
+        self.metallic = metallic;
+        self
+    }
+
+    /// Multiplies the green channel of the `metallic_roughness` texture
+    pub fn roughness(mut self, roughness: Real) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn metallic_roughness(mut self, texture: Arc<Box<dyn Texture + Send + Sync>>) -> Self {
+        self.metallic_roughness = Some(texture);
+        self
+    }
+
+    /// Base color, metallic and GGX alpha at the hit point
+    fn parameters(&self, record: &HitRecord) -> (Vec3, Real, Real) {
+        let base_color = self.base_color.value_at(record);
+        let (mut metallic, mut roughness) = (self.metallic, self.roughness);
+        if let Some(texture) = &self.metallic_roughness {
+            let value = texture.value_at(record);
+            roughness *= value.y;
+            metallic *= value.z;
+        }
+        let alpha = (roughness * roughness).clamp(Self::MIN_ALPHA, 1.0);
+        (base_color, metallic.clamp(0.0, 1.0), alpha)
+    }
+}
+
+impl Material for Principled {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        _rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        let (_, metallic, alpha) = self.parameters(record);
+        // metals have no diffuse part worth sampling
+        let specular = (1.0 + metallic) / 2.0;
+        let outgoing = -ray.direction.normalize();
+        Some(ScatterRecord::Pdf {
+            attenuation: Vec3::ONE,
+            pdf: Box::new(MicrofacetPdf::new(record.normal, outgoing, alpha, specular)),
+        })
+    }
+
+    fn scattering(&self, ray: &Ray, record: &HitRecord, scattered: &Ray) -> Vec3 {
+        let (base_color, metallic, alpha) = self.parameters(record);
+        let outgoing = -ray.direction.normalize();
+        let incoming = scattered.direction.normalize();
+        let cos_outgoing = record.normal.dot(outgoing);
+        let cos_incoming = record.normal.dot(incoming);
+        if cos_outgoing <= 0.0 || cos_incoming <= 0.0 {
+            return Vec3::ZERO;
+        }
+
+        // Schlick: a refraction index of 1.5 gives the 4% of dielectrics, 1.0 the bare weight
+        let half = (outgoing + incoming).normalize();
+        let cos_half = incoming.dot(half);
+        let dielectric = Vec3::splat(reflectance(cos_half, 1.5));
+        let metal = base_color + (1.0 - base_color) * reflectance(cos_half, 1.0);
+        let fresnel = dielectric.lerp(metal, metallic);
+
+        let specular = fresnel
+            * ggx_distribution(record.normal.dot(half), alpha)
+            * ggx_smith_g1(cos_outgoing, alpha)
+            * ggx_smith_g1(cos_incoming, alpha)
+            / (4.0 * cos_outgoing);
+        let diffuse = (1.0 - fresnel) * (1.0 - metallic) * base_color * cos_incoming / PI;
+        specular + diffuse
+    }
+}
+
+/// Refraction index as a function of the wavelength
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum RefractiveIndex {
+    Constant(Real),
+    /// `a + b / λ²`, λ in micrometers
+    Cauchy {
+        a: Real,
+        b: Real,
+    },
+    /// `1 + Σ b λ² / (λ² - c)`, λ in micrometers
+    Sellmeier {
+        b: [Real; 3],
+        c: [Real; 3],
+    },
+}
+
+impl RefractiveIndex {
+    /// Wavelengths in micrometers that stand for the red, green and blue channels
+    pub const WAVELENGTHS: [Real; 3] = [0.65, 0.55, 0.45];
+
+    /// Dense flint glass (Schott SF11)
+    pub const FLINT: RefractiveIndex = RefractiveIndex::Sellmeier {
+        b: [1.737_597, 0.313_747, 1.898_781],
+        c: [0.013_188_7, 0.062_306_8, 155.236_3],
+    };
+
+    pub fn at(&self, wavelength: Real) -> Real {
+        let lambda2 = wavelength * wavelength;
+        match *self {
+            RefractiveIndex::Constant(index) => index,
+            RefractiveIndex::Cauchy { a, b } => a + b / lambda2,
+            RefractiveIndex::Sellmeier { b, c } => {
+                let sum = (0..3)
+                    .map(|i| b[i] * lambda2 / (lambda2 - c[i]))
+                    .sum::<Real>();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+}
+
 pub struct Dielectric {
-    refraction_index: f32,
+    refraction_index: RefractiveIndex,
+    /// Beer–Lambert absorption coefficients, per unit of distance traveled inside
+    absorption: Vec3,
 }
 
 impl Default for Dielectric {
     fn default() -> Self {
-        Dielectric {
-            refraction_index: 1.5,
-        }
+        Dielectric::new(1.5)
     }
 }
 
 impl Dielectric {
-    pub fn new(refraction_index: f32) -> Self {
-        Dielectric { refraction_index }
+    pub fn new(refraction_index: Real) -> Self {
+        Dielectric::dispersive(RefractiveIndex::Constant(refraction_index))
+    }
+
+    /// Refracts every color channel with the index of its wavelength, see
+    /// [`RefractiveIndex::WAVELENGTHS`]
+    pub fn dispersive(refraction_index: RefractiveIndex) -> Self {
+        Dielectric {
+            refraction_index,
+            absorption: Vec3::ZERO,
+        }
+    }
+
+    pub fn absorption(mut self, absorption: Vec3) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    /// Absorbs so that white light comes out as `color` after traveling `distance` inside,
+    /// every channel of `color` must be in `(0, 1]`
+    pub fn tint(self, color: Vec3, distance: Real) -> Self {
+        assert!(
+            distance > 0.0,
+            "tint distance must be positive, got {distance}"
+        );
+        assert!(
+            color.cmpgt(Vec3::ZERO).all() && color.cmple(Vec3::ONE).all(),
+            "tint color must be in (0, 1], got {color}"
+        );
+        let absorption = color.to_array().map(|c| -c.ln() / distance);
+        self.absorption(Vec3::from_array(absorption))
     }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Vec3, Ray)> {
-        let attenuation = Vec3::new(1.0, 1.0, 1.0); // glass doesn't absorb any light
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        // leaving through a back face, the ray traveled inside all along
+        let mut attenuation = if record.front_face {
+            Vec3::ONE
+        } else {
+            let distance = record.t * ray.direction.length();
+            (-self.absorption * distance).exp()
+        };
 
+        let (refraction_index, channel) = match self.refraction_index {
+            RefractiveIndex::Constant(index) => (index, ray.channel),
+            // the first dispersive interface of a path picks one channel for all the following
+            // ones, weighted by 3 so that the average stays the same
+            index => {
+                let channel = ray.channel.unwrap_or_else(|| {
+                    let channel = rng.gen_range(0..3);
+                    attenuation *= 3.0 * Vec3::AXES[channel];
+                    channel
+                });
+                let refraction_index = index.at(RefractiveIndex::WAVELENGTHS[channel]);
+                (refraction_index, Some(channel))
+            }
+        };
         let ri = if record.front_face {
-            1.0 / self.refraction_index
+            1.0 / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
 
         let cos_theta = (-ray.direction.normalize()).dot(record.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        let scattered = if ri * sin_theta > 1.0 || reflectance(cos_theta, ri) > random() {
+        let scattered = if ri * sin_theta > 1.0 || reflectance(cos_theta, ri) > rng.gen() {
             ray.direction.reflect(record.normal)
         } else {
             refract(ray.direction.normalize(), record.normal, ri)
         };
 
-        let scattered_ray = Ray::new(record.point, scattered);
+        let scattered_ray = Ray::new(record.point, scattered)
+            .with_time(ray.time)
+            .with_channel(channel);
+
+        Some(ScatterRecord::Specular {
+            attenuation,
+            ray: scattered_ray,
+        })
+    }
+}
+
+pub struct DiffuseLight {
+    texture: Arc<Box<dyn Texture + Send + Sync>>,
+}
+
+impl DiffuseLight {
+    pub fn new(texture: Arc<Box<dyn Texture + Send + Sync>>) -> Self {
+        DiffuseLight { texture }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _record: &HitRecord,
+        _rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, u: Real, v: Real, point: Vec3) -> Vec3 {
+        self.texture.value(u, v, point)
+    }
+}
+
+/// Where the shading normal of [`NormalMapped`] comes from
+pub enum NormalMap {
+    /// Tangent space normals encoded as colors, the blue-ish images most assets come with
+    Normals(Arc<Box<dyn Texture + Send + Sync>>),
+    /// Heights over uv in the red channel, whose slope tilts the normal by `strength`
+    Heights {
+        texture: Arc<Box<dyn Texture + Send + Sync>>,
+        strength: Real,
+    },
+}
+
+/// Shades `material` with the normal perturbed by a [`NormalMap`], objects without tangents
+/// keep their geometric normal
+pub struct NormalMapped {
+    material: MaterialRef,
+    map: NormalMap,
+}
+
+impl NormalMapped {
+    /// uv step of the central differences of a height map
+    const DELTA: Real = 1e-3;
 
-        Some((attenuation, scattered_ray))
+    pub fn new(material: MaterialRef, map: NormalMap) -> Self {
+        NormalMapped { material, map }
+    }
+
+    fn shading_normal(&self, record: &HitRecord) -> Vec3 {
+        // maps are relative to the outward normal, which back faces flip along with the result
+        let normal = if record.front_face {
+            record.normal
+        } else {
+            -record.normal
+        };
+        // the tangents of smooth shaded triangles are not perpendicular to their normal
+        let tangent = (record.tangent - normal.dot(record.tangent) * normal).normalize_or_zero();
+        if tangent == Vec3::ZERO {
+            return record.normal;
+        }
+        let bitangent = record.bitangent
+            - normal.dot(record.bitangent) * normal
+            - tangent.dot(record.bitangent) * tangent;
+        let bitangent = bitangent.normalize_or(normal.cross(tangent));
+
+        let perturbed = match &self.map {
+            NormalMap::Normals(texture) => {
+                let local = 2.0 * texture.value_at(record) - 1.0;
+                local.x * tangent + local.y * bitangent + local.z * normal
+            }
+            NormalMap::Heights { texture, strength } => {
+                let (u, v, d) = (record.u, record.v, Self::DELTA);
+                let footprint = record.footprint;
+                let height = |u, v| texture.filtered_value(u, v, record.point, footprint).x;
+                let du = (height(u + d, v) - height(u - d, v)) / (2.0 * d);
+                let dv = (height(u, v + d) - height(u, v - d)) / (2.0 * d);
+                normal - strength * (du * tangent + dv * bitangent)
+            }
+        };
+        let perturbed = perturbed.normalize_or(normal);
+        if record.front_face {
+            perturbed
+        } else {
+            -perturbed
+        }
+    }
+
+    fn perturbed(&self, record: &HitRecord) -> HitRecord {
+        HitRecord {
+            normal: self.shading_normal(record),
+            ..record.clone()
+        }
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        self.material.scatter(ray, &self.perturbed(record), rng)
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, record: &HitRecord, scattered: &Ray) -> Real {
+        self.material
+            .scattering_pdf(ray, &self.perturbed(record), scattered)
+    }
+
+    fn scattering(&self, ray: &Ray, record: &HitRecord, scattered: &Ray) -> Vec3 {
+        self.material
+            .scattering(ray, &self.perturbed(record), scattered)
+    }
+
+    fn emitted(&self, u: Real, v: Real, point: Vec3) -> Vec3 {
+        self.material.emitted(u, v, point)
+    }
+}
+
+/// Phase function of a participating medium, scatters uniformly in all directions
+pub struct Isotropic {
+    texture: Arc<Box<dyn Texture + Send + Sync>>,
+}
+
+impl Isotropic {
+    pub fn new(texture: Arc<Box<dyn Texture + Send + Sync>>) -> Self {
+        Isotropic { texture }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        record: &HitRecord,
+        _rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        let attenuation = self.texture.value_at(record);
+        Some(ScatterRecord::Pdf {
+            attenuation,
+            pdf: Box::new(SpherePdf),
+        })
+    }
+
+    fn scattering_pdf(&self, _ray: &Ray, _record: &HitRecord, _scattered: &Ray) -> Real {
+        1.0 / (4.0 * PI)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use crate::texture::SolidColor;
+
+    use super::*;
+
+    /// A hit at the origin, without texture coordinates or tangents
+    fn record(normal: Vec3, front_face: bool) -> HitRecord {
+        HitRecord {
+            point: Vec3::ZERO,
+            normal,
+            t: 1.0,
+            front_face,
+            material: None,
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            footprint: 0.0,
+        }
+    }
+
+    /// Fraction of the light from every direction a surface reflects towards `outgoing`,
+    /// estimated by importance sampling the material's own pdf
+    fn albedo(material: &dyn Material, outgoing: Vec3) -> Vec3 {
+        const SAMPLES: u32 = 100000;
+
+        let record = record(Vec3::Y, true);
+        let ray = Ray::new(outgoing, -outgoing);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut sum = Vec3::ZERO;
+        for _ in 0..SAMPLES {
+            let Some(ScatterRecord::Pdf { attenuation, pdf }) =
+                material.scatter(&ray, &record, &mut rng)
+            else {
+                panic!("expected a pdf");
+            };
+            let direction = pdf.generate(&mut rng);
+            let pdf_value = pdf.value(direction);
+            if pdf_value > 0.0 {
+                let scattered = Ray::new(Vec3::ZERO, direction);
+                sum += attenuation * material.scattering(&ray, &record, &scattered) / pdf_value;
+            }
+        }
+        sum / SAMPLES as Real
+    }
+
+    #[test]
+    fn test_principled_energy() {
+        let white = || -> Arc<Box<dyn Texture + Send + Sync>> {
+            Arc::new(Box::new(SolidColor::new(Vec3::ONE)))
+        };
+        let outgoing = Vec3::new(0.6, 0.8, 0.0);
+
+        // what the microfacets don't reflect the white base does
+        for roughness in [0.0, 0.5, 1.0] {
+            let material = Principled::new(white()).roughness(roughness);
+            let albedo = albedo(&material, outgoing);
+            assert!((0.95..1.01).contains(&albedo.x), "{roughness}: {albedo}");
+        }
+
+        // a smooth metal is a mirror, light bouncing between the microfacets more than once
+        // is lost, which only matters for rough ones
+        for (roughness, expected) in [(0.0, 1.0), (0.5, 0.9), (1.0, 0.34)] {
+            let material = Principled::new(white()).metallic(1.0).roughness(roughness);
+            let albedo = albedo(&material, outgoing);
+            assert!((albedo.x - expected).abs() < 0.02, "{roughness}: {albedo}");
+        }
+    }
+
+    #[test]
+    fn test_dielectric_absorption() {
+        let glass = Dielectric::new(1.5).tint(Vec3::new(0.5, 0.25, 1.0), 1.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        // leaving the glass after traveling 2 inside
+        let ray = Ray::new(Vec3::new(0.0, -2.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+        let record = record(Vec3::NEG_Y, false);
+        let Some(ScatterRecord::Specular { attenuation, .. }) =
+            glass.scatter(&ray, &record, &mut rng)
+        else {
+            panic!("expected a specular scatter");
+        };
+        assert!(attenuation.abs_diff_eq(Vec3::new(0.25, 0.0625, 1.0), 1e-5));
+
+        // entering it absorbs nothing yet
+        let record = HitRecord {
+            normal: Vec3::Y,
+            front_face: true,
+            ..record
+        };
+        let ray = Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -2.0, 0.0));
+        let Some(ScatterRecord::Specular { attenuation, .. }) =
+            glass.scatter(&ray, &record, &mut rng)
+        else {
+            panic!("expected a specular scatter");
+        };
+        assert_eq!(attenuation, Vec3::ONE);
+    }
+
+    #[test]
+    fn test_dispersion() {
+        let flint = RefractiveIndex::FLINT;
+        assert!((flint.at(0.5876) - 1.785).abs() < 1e-3);
+        let [red, green, blue] = RefractiveIndex::WAVELENGTHS.map(|w| flint.at(w));
+        assert!(red < green && green < blue);
+
+        // blue bends the most towards the normal
+        let glass = Dielectric::dispersive(flint);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let ray = Ray::new(Vec3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        let record = record(Vec3::Y, true);
+        let mut cos_refracted = [None; 3];
+        for _ in 0..100 {
+            let Some(ScatterRecord::Specular { attenuation, ray }) =
+                glass.scatter(&ray, &record, &mut rng)
+            else {
+                panic!("expected a specular scatter");
+            };
+            assert_eq!(attenuation.element_sum(), 3.0);
+            let channel = Vec3::AXES
+                .iter()
+                .position(|&axis| attenuation.dot(axis) > 0.0);
+            let cos = -ray.direction.normalize().y;
+            if cos > 0.0 {
+                cos_refracted[channel.unwrap()] = Some(cos);
+            }
+        }
+        let [Some(red), Some(green), Some(blue)] = cos_refracted else {
+            panic!("every channel should have refracted");
+        };
+        assert!(red < green && green < blue);
+
+        // a dispersed ray keeps its channel through the next interfaces, weighted only once
+        let ray = ray.with_channel(Some(2));
+        for _ in 0..100 {
+            let Some(ScatterRecord::Specular {
+                attenuation,
+                ray: scattered,
+            }) = glass.scatter(&ray, &record, &mut rng)
+            else {
+                panic!("expected a specular scatter");
+            };
+            assert_eq!(attenuation, Vec3::ONE);
+            assert_eq!(scattered.channel, Some(2));
+            let cos = -scattered.direction.normalize().y;
+            assert!(cos < 0.0 || (cos - blue).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "tint color must be in (0, 1]")]
+    fn test_dielectric_tint_out_of_range() {
+        let _ = Dielectric::new(1.5).tint(Vec3::new(0.5, 1.5, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_normal_mapped() {
+        let solid = |color: Vec3| -> Arc<Box<dyn Texture + Send + Sync>> {
+            Arc::new(Box::new(SolidColor::new(color)))
+        };
+        let material: MaterialRef = Arc::new(Box::new(Lambertian::new(solid(Vec3::ONE))));
+        let record = HitRecord {
+            u: 0.5,
+            v: 0.5,
+            tangent: Vec3::X,
+            bitangent: Vec3::NEG_Z,
+            ..record(Vec3::Y, true)
+        };
+        let shading_normal = |map| NormalMapped::new(material.clone(), map).shading_normal(&record);
+
+        // the flat color of normal maps leaves the normal alone
+        let flat = NormalMap::Normals(solid(Vec3::new(0.5, 0.5, 1.0)));
+        assert!(shading_normal(flat).abs_diff_eq(Vec3::Y, 1e-5));
+        let tilted = NormalMap::Normals(solid(Vec3::new(1.0, 0.5, 1.0)));
+        let expected = Vec3::new(1.0, 1.0, 0.0).normalize();
+        assert!(shading_normal(tilted).abs_diff_eq(expected, 1e-5));
+
+        // seen from behind it is the same surface, tilted the same way
+        let back = HitRecord {
+            normal: Vec3::NEG_Y,
+            front_face: false,
+            ..record.clone()
+        };
+        let tilted = NormalMapped::new(
+            material.clone(),
+            NormalMap::Normals(solid(Vec3::new(1.0, 0.5, 1.0))),
+        );
+        assert!(tilted.shading_normal(&back).abs_diff_eq(-expected, 1e-5));
+
+        // so does a height map without slope
+        let heights = NormalMap::Heights {
+            texture: solid(Vec3::ONE),
+            strength: 1.0,
+        };
+        assert!(shading_normal(heights).abs_diff_eq(Vec3::Y, 1e-5));
+
+        // and objects without tangents
+        let record = HitRecord {
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            footprint: 0.0,
+            ..record
+        };
+        let tilted = NormalMapped::new(
+            material.clone(),
+            NormalMap::Normals(solid(Vec3::new(1.0, 0.5, 1.0))),
+        );
+        assert_eq!(tilted.shading_normal(&record), Vec3::Y);
     }
 }
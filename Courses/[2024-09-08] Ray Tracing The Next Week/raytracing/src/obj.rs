@@ -0,0 +1,273 @@
+//! Wavefront OBJ (+ MTL) loading
+//!
+//! MTL materials are mapped onto the existing materials:
+//! - an emission color (`Ke`) becomes a [`DiffuseLight`]
+//! - a transparent material (`d < 1` or `illum` 4, 6, 7, 9) becomes a [`Dielectric`] with `Ni`
+//! - a reflective material (`illum` 3, 5, 8) becomes a [`Metal`] with `Ks`, whose fuzz is
+//!   derived from the shininess `Ns`
+//! - everything else becomes a [`Lambertian`] with `map_Kd` as an [`ImageTexture`] or `Kd`
+//!   as a [`SolidColor`]
+
+use std::{fmt, path::Path, sync::Arc};
+
+use log::warn;
+
+use crate::{
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    math::{from_f32_array, Real, Vec2, Vec3},
+    primitive::TriangleMesh,
+    texture::{Filter, ImageTexture, SolidColor, Texture, Wrap},
+    world::bvh::{AabbHittable, Bvh},
+    MaterialRef,
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Load(tobj::LoadError),
+    Texture {
+        path: String,
+        source: image::ImageError,
+    },
+    /// The file contains no triangles
+    Empty,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Load(err) => write!(f, "failed to load obj: {err}"),
+            ObjError::Texture { path, source } => {
+                write!(f, "failed to load texture {path:?}: {source}")
+            }
+            ObjError::Empty => write!(f, "obj contains no triangles"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Load(err) => Some(err),
+            ObjError::Texture { source, .. } => Some(source),
+            ObjError::Empty => None,
+        }
+    }
+}
+
+impl From<tobj::LoadError> for ObjError {
+    fn from(err: tobj::LoadError) -> Self {
+        ObjError::Load(err)
+    }
+}
+
+/// Loads every model in the obj file as a [`TriangleMesh`], all of them under one [`Bvh`]
+///
+/// `default_material` is used for models without a (loadable) MTL material.
+pub fn load_obj(
+    path: impl AsRef<Path>,
+    default_material: Arc<Box<dyn Material + Send + Sync>>,
+) -> Result<Bvh, ObjError> {
+    let path = path.as_ref();
+    let (models, materials) = tobj::load_obj(
+        path,
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+    )?;
+
+    let materials = match materials {
+        Ok(materials) => materials,
+        Err(err) => {
+            warn!("failed to load mtl of {path:?}, using the default material: {err}");
+            Vec::new()
+        }
+    };
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let materials = materials
+        .iter()
+        .map(|material| convert_material(material, base_dir))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let meshes = models
+        .iter()
+        .filter(|model| !model.mesh.indices.is_empty())
+        .map(|model| {
+            let mesh = &model.mesh;
+            let positions = reals(&mesh.positions)
+                .chunks_exact(3)
+                .map(Vec3::from_slice)
+                .collect::<Vec<_>>();
+            let normals = reals(&mesh.normals)
+                .chunks_exact(3)
+                .map(Vec3::from_slice)
+                .collect::<Vec<_>>();
+            let uvs = reals(&mesh.texcoords)
+                .chunks_exact(2)
+                .map(Vec2::from_slice)
+                .collect::<Vec<_>>();
+            let indices = mesh
+                .indices
+                .chunks_exact(3)
+                .map(|i| [i[0], i[1], i[2]])
+                .collect::<Vec<_>>();
+
+            let material = mesh
+                .material_id
+                .and_then(|id| materials.get(id))
+                .unwrap_or(&default_material)
+                .clone();
+
+            let mesh = TriangleMesh::new(
+                &positions,
+                (!normals.is_empty()).then_some(normals.as_slice()),
+                (!uvs.is_empty()).then_some(uvs.as_slice()),
+                &indices,
+                material,
+            );
+            Box::new(mesh) as Box<dyn AabbHittable<MaterialRef> + Send + Sync>
+        })
+        .collect::<Vec<_>>();
+
+    if meshes.is_empty() {
+        return Err(ObjError::Empty);
+    }
+    Ok(Bvh::from_objects(meshes))
+}
+
+/// tobj always loads `f32`
+fn reals(values: &[f32]) -> Vec<Real> {
+    values.iter().map(|&x| x as Real).collect()
+}
+
+fn convert_material(
+    material: &tobj::Material,
+    base_dir: &Path,
+) -> Result<Arc<Box<dyn Material + Send + Sync>>, ObjError> {
+    let color = |c: Option<[f32; 3]>, default: Vec3| c.map(from_f32_array).unwrap_or(default);
+    let solid = |color: Vec3| -> Arc<Box<dyn Texture + Send + Sync>> {
+        Arc::new(Box::new(SolidColor::new(color)))
+    };
+
+    let emission = material
+        .unknown_param
+        .get("Ke")
+        .and_then(|ke| {
+            let ke = ke
+                .split_whitespace()
+                .map(|x| x.parse::<Real>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            (ke.len() == 3).then(|| Vec3::from_slice(&ke))
+        })
+        .unwrap_or(Vec3::ZERO);
+    if emission.max_element() > 0.0 {
+        return Ok(Arc::new(Box::new(DiffuseLight::new(solid(emission)))));
+    }
+
+    let illum = material.illumination_model.unwrap_or(2);
+    if material.dissolve.is_some_and(|d| d < 1.0) || matches!(illum, 4 | 6 | 7 | 9) {
+        let refraction_index = material.optical_density.unwrap_or(1.5);
+        return Ok(Arc::new(Box::new(Dielectric::new(
+            refraction_index as Real,
+        ))));
+    }
+
+    if matches!(illum, 3 | 5 | 8) {
+        // Phong exponent -> roughness, Ns = 0 is fully rough and Ns = 1000 is a mirror
+        let shininess = material.shininess.unwrap_or(0.0).max(0.0) as Real;
+        let fuzz = (2.0 / (shininess + 2.0)).sqrt().min(1.0);
+        let albedo = color(material.specular, Vec3::splat(0.8));
+        return Ok(Arc::new(Box::new(Metal::new(solid(albedo)).fuzz(fuzz))));
+    }
+
+    let texture: Arc<Box<dyn Texture + Send + Sync>> = match &material.diffuse_texture {
+        Some(texture_path) => {
+            let full_path = base_dir.join(texture_path);
+            let texture = ImageTexture::from_path(&full_path, None)
+                .map_err(|source| ObjError::Texture {
+                    path: full_path.display().to_string(),
+                    source,
+                })?
+                .filter(Filter::Trilinear)
+                .wrap(Wrap::Repeat);
+            Arc::new(Box::new(texture))
+        }
+        None => solid(color(material.diffuse, Vec3::splat(0.8))),
+    };
+    Ok(Arc::new(Box::new(Lambertian::new(texture))))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use crate::{material::ScatterRecord, Hittable, Ray};
+
+    use super::*;
+
+    #[test]
+    fn test_load_obj() {
+        let dir =
+            std::env::temp_dir().join(format!("raytracing-test-load-obj-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("quad.mtl"),
+            "newmtl light\nKd 1 1 1\nKe 4 4 4\n\nnewmtl glass\nillum 7\nNi 1.33\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("quad.obj"),
+            "mtllib quad.mtl\n\
+             v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\n\
+             vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             o light\nusemtl light\nf 1/1 2/2 3/3 4/4\n\
+             v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+             o glass\nusemtl glass\nf 5 6 7 8\n",
+        )
+        .unwrap();
+
+        let default_material: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+            Lambertian::new(Arc::new(Box::new(SolidColor::new(Vec3::ONE)))),
+        ));
+        let world = load_obj(dir.join("quad.obj"), default_material).unwrap();
+
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 1.0), Vec3::NEG_Z);
+        let record = world.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((record.t - 1.0).abs() < 1e-5);
+        assert!((record.u - 0.75).abs() < 1e-5);
+        assert!((record.v - 0.75).abs() < 1e-5);
+        let emitted = record
+            .material
+            .unwrap()
+            .emitted(record.u, record.v, record.point);
+        assert_eq!(emitted, Vec3::splat(4.0));
+
+        // only a dielectric lets light through, bent following `Ni`
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -0.5), Vec3::new(1.0, 0.0, -1.0));
+        let record = world.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        let material = record.material.clone().unwrap();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let refracted = (0..100)
+            .find_map(|_| match material.scatter(&ray, &record, &mut rng) {
+                Some(ScatterRecord::Specular { attenuation, ray }) if ray.direction.z < 0.0 => {
+                    assert_eq!(attenuation, Vec3::ONE);
+                    Some(ray.direction.normalize())
+                }
+                _ => None,
+            })
+            .unwrap();
+        let sin_theta = (0.5 as Real).sqrt() / 1.33;
+        assert!((refracted.x - sin_theta).abs() < 1e-5);
+
+        assert!(matches!(
+            load_obj(
+                dir.join("missing.obj"),
+                Arc::new(Box::new(Dielectric::default()))
+            ),
+            Err(ObjError::Load(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
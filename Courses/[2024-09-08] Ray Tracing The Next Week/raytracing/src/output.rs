@@ -0,0 +1,117 @@
+//! Saving rendered framebuffers
+//!
+//! The format follows the extension of the path:
+//! - `.exr` (OpenEXR) and `.hdr` (Radiance HDR) keep the linear radiance as is
+//! - anything else (`.png`, `.jpg`, ...) is tone mapped to 8-bit first, see [`to_rgb8`]
+
+use std::path::Path;
+
+use image::{ImageResult, Rgb, Rgb32FImage, RgbImage};
+
+use crate::math::{from_f32_array, Vec3};
+use crate::tonemap::ToneMapping;
+
+/// Whether the path is saved with linear radiance instead of 8-bit colors
+pub fn is_hdr_path(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exr") || ext.eq_ignore_ascii_case("hdr"))
+}
+
+/// Saves a linear framebuffer, in the format given by the path's extension
+///
+/// `tone_mapping` is only used for 8-bit formats.
+pub fn save(
+    framebuffer: &Rgb32FImage,
+    path: impl AsRef<Path>,
+    tone_mapping: &ToneMapping,
+) -> ImageResult<()> {
+    let path = path.as_ref();
+    if is_hdr_path(path) {
+        framebuffer.save(path)
+    } else {
+        to_rgb8(framebuffer, tone_mapping).save(path)
+    }
+}
+
+/// Fails like [`save`] would, without touching the file already at `path`
+///
+/// A 1x1 image with the same extension is saved next to it and removed again.
+pub fn check_writable(path: impl AsRef<Path>) -> ImageResult<()> {
+    let path = path.as_ref();
+    let extension = path.extension().unwrap_or_default();
+    let probe = path
+        .with_file_name(format!(".probe-{}", std::process::id()))
+        .with_extension(extension);
+    save(&Rgb32FImage::new(1, 1), &probe, &ToneMapping::default())?;
+    std::fs::remove_file(probe)?;
+    Ok(())
+}
+
+/// Tone maps and quantizes a linear framebuffer
+pub fn to_rgb8(framebuffer: &Rgb32FImage, tone_mapping: &ToneMapping) -> RgbImage {
+    RgbImage::from_fn(framebuffer.width(), framebuffer.height(), |x, y| {
+        let color = tone_mapping.apply(from_f32_array(framebuffer.get_pixel(x, y).0));
+        let color_u8 = (255.999 * color).clamp(Vec3::ZERO, Vec3::splat(255.0));
+        Rgb([color_u8.x as u8, color_u8.y as u8, color_u8.z as u8])
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_save() {
+        let framebuffer =
+            Rgb32FImage::from_fn(4, 2, |x, y| Rgb([x as f32 * 4.0, y as f32 * 0.25, 0.0625]));
+
+        let dir = std::env::temp_dir().join(format!("raytracing-test-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // linear radiance above 1 survives the round trip
+        let tone_mapping = ToneMapping::default();
+        save(&framebuffer, dir.join("image.exr"), &tone_mapping).unwrap();
+        let exr = image::open(dir.join("image.exr")).unwrap().to_rgb32f();
+        assert_eq!(exr, framebuffer);
+
+        save(&framebuffer, dir.join("image.hdr"), &tone_mapping).unwrap();
+        let hdr = image::open(dir.join("image.hdr")).unwrap().to_rgb32f();
+        for (a, b) in hdr.pixels().zip(framebuffer.pixels()) {
+            let (a, b) = (glam::Vec3::from(a.0), glam::Vec3::from(b.0));
+            assert!(a.abs_diff_eq(b, 0.05 * b.x.max(1.0)));
+        }
+
+        save(&framebuffer, dir.join("image.png"), &tone_mapping).unwrap();
+        let png = image::open(dir.join("image.png")).unwrap().to_rgb8();
+        assert_eq!(png.get_pixel(0, 1), &Rgb([0, 137, 70]));
+        assert_eq!(png.get_pixel(3, 0), &Rgb([255, 0, 70]));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = std::env::temp_dir().join(format!(
+            "raytracing-test-check-writable-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // a previous render is left alone, and so is the directory
+        let framebuffer = Rgb32FImage::from_pixel(2, 2, Rgb([0.5; 3]));
+        save(&framebuffer, dir.join("image.png"), &ToneMapping::default()).unwrap();
+        let before = std::fs::read(dir.join("image.png")).unwrap();
+        check_writable(dir.join("image.png")).unwrap();
+        assert_eq!(std::fs::read(dir.join("image.png")).unwrap(), before);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        check_writable(dir.join("image.exr")).unwrap();
+        assert!(!dir.join("image.exr").exists());
+        assert!(check_writable(dir.join("missing/image.png")).is_err());
+        assert!(check_writable(dir.join("image.unknown")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
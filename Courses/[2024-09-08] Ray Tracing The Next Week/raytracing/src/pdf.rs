@@ -0,0 +1,201 @@
+//! Probability density functions over directions, used for importance sampling
+
+use rand::{Rng, RngCore};
+
+use crate::{
+    background::Background,
+    math::{consts::PI, Real, Vec3},
+    utils::{random_cosine_direction, random_in_unit_sphere, Onb},
+    Sampleable,
+};
+
+pub trait Pdf {
+    /// Density of generating `direction`
+    fn value(&self, direction: Vec3) -> Real;
+    /// Generates a random direction following this density
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3;
+}
+
+/// Uniform over all directions
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> Real {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3 {
+        random_in_unit_sphere(rng).normalize()
+    }
+}
+
+/// Cosine weighted around a normal, matches a lambertian surface
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: Vec3) -> Self {
+        CosinePdf {
+            uvw: Onb::new(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> Real {
+        let cosine_theta = direction.normalize().dot(self.uvw.w);
+        (cosine_theta / PI).max(0.0)
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3 {
+        self.uvw.transform(random_cosine_direction(rng))
+    }
+}
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, `cos_theta` is the cosine
+/// between a microfacet normal and the surface normal
+pub fn ggx_distribution(cos_theta: Real, alpha: Real) -> Real {
+    let alpha2 = alpha * alpha;
+    let d = cos_theta * cos_theta * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * d * d)
+}
+
+/// Smith masking-shadowing of GGX microfacets seen from a direction at `cos_theta` to the
+/// surface normal
+pub fn ggx_smith_g1(cos_theta: Real, alpha: Real) -> Real {
+    let alpha2 = alpha * alpha;
+    2.0 * cos_theta / (cos_theta + (alpha2 + (1.0 - alpha2) * cos_theta * cos_theta).sqrt())
+}
+
+/// What a microfacet surface scatters with: mirrored about GGX distributed microfacet
+/// normals with probability `specular`, cosine weighted around the normal otherwise
+pub struct MicrofacetPdf {
+    uvw: Onb,
+    /// Unit vector towards where the scattered light goes
+    outgoing: Vec3,
+    alpha: Real,
+    specular: Real,
+}
+
+impl MicrofacetPdf {
+    pub fn new(normal: Vec3, outgoing: Vec3, alpha: Real, specular: Real) -> Self {
+        MicrofacetPdf {
+            uvw: Onb::new(normal),
+            outgoing,
+            alpha,
+            specular,
+        }
+    }
+}
+
+impl Pdf for MicrofacetPdf {
+    fn value(&self, direction: Vec3) -> Real {
+        let direction = direction.normalize();
+        let cos_theta = direction.dot(self.uvw.w);
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+
+        // density of the half vector, times the jacobian of mirroring about it
+        let half = (direction + self.outgoing).normalize();
+        let cos_half = half.dot(self.uvw.w);
+        let specular = ggx_distribution(cos_half, self.alpha) * cos_half.max(0.0)
+            / (4.0 * half.dot(self.outgoing).abs().max(Real::EPSILON));
+        self.specular * specular + (1.0 - self.specular) * cos_theta / PI
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3 {
+        if rng.gen::<Real>() >= self.specular {
+            return self.uvw.transform(random_cosine_direction(rng));
+        }
+
+        // inverse cdf of `ggx_distribution * cos_theta`
+        let r1 = rng.gen::<Real>();
+        let r2 = rng.gen::<Real>();
+        let phi = 2.0 * PI * r1;
+        let cos2_theta = (1.0 - r2) / (1.0 + (self.alpha * self.alpha - 1.0) * r2);
+        let sin_theta = (1.0 - cos2_theta).max(0.0).sqrt();
+        let half = self.uvw.transform(Vec3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos2_theta.sqrt(),
+        ));
+        (-self.outgoing).reflect(half)
+    }
+}
+
+/// Directions from `origin` towards an object at `time`, see [`Sampleable::pdf_value`] and
+/// [`Sampleable::random`]
+pub struct HittablePdf<'a> {
+    object: &'a dyn Sampleable,
+    origin: Vec3,
+    time: Real,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(object: &'a dyn Sampleable, origin: Vec3, time: Real) -> Self {
+        HittablePdf {
+            object,
+            origin,
+            time,
+        }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: Vec3) -> Real {
+        self.object.pdf_value(self.origin, direction, self.time)
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3 {
+        self.object.random(self.origin, self.time, rng)
+    }
+}
+
+/// Directions towards the bright parts of a background, see [`Background::pdf_value`] and
+/// [`Background::random`]
+pub struct BackgroundPdf<'a> {
+    background: &'a dyn Background,
+}
+
+impl<'a> BackgroundPdf<'a> {
+    pub fn new(background: &'a dyn Background) -> Self {
+        BackgroundPdf { background }
+    }
+}
+
+impl Pdf for BackgroundPdf<'_> {
+    fn value(&self, direction: Vec3) -> Real {
+        self.background.pdf_value(direction)
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3 {
+        self.background.random(rng)
+    }
+}
+
+/// Picks one of two pdfs with equal probability
+pub struct MixturePdf<'a> {
+    pdfs: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(a: &'a dyn Pdf, b: &'a dyn Pdf) -> Self {
+        MixturePdf { pdfs: [a, b] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: Vec3) -> Real {
+        0.5 * self.pdfs[0].value(direction) + 0.5 * self.pdfs[1].value(direction)
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3 {
+        if rng.gen::<Real>() < 0.5 {
+            self.pdfs[0].generate(rng)
+        } else {
+            self.pdfs[1].generate(rng)
+        }
+    }
+}
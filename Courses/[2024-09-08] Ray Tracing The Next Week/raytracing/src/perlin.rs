@@ -0,0 +1,132 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::math::{Real, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+/// Perlin gradient noise, the same seed always gives the same noise
+pub struct Perlin {
+    /// random unit vectors on the lattice points
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                let v = Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+                let length_squared = v.length_squared();
+                if length_squared > Real::EPSILON && length_squared <= 1.0 {
+                    break v.normalize();
+                }
+            })
+            .collect();
+
+        let mut generate_perm = || {
+            let mut perm = (0..POINT_COUNT).collect::<Vec<_>>();
+            perm.shuffle(&mut rng);
+            perm
+        };
+        let perm_x = generate_perm();
+        let perm_y = generate_perm();
+        let perm_z = generate_perm();
+
+        Perlin {
+            gradients,
+            perm_x,
+            perm_y,
+            perm_z,
+        }
+    }
+
+    /// Noise value in about `[-1, 1]`
+    pub fn noise(&self, point: Vec3) -> Real {
+        let floor = point.floor();
+        let f = point - floor;
+        let [i, j, k] = floor.to_array().map(|x| x as i32);
+
+        let mut c = [[[Vec3::ZERO; 2]; 2]; 2];
+        for (di, c) in c.iter_mut().enumerate() {
+            for (dj, c) in c.iter_mut().enumerate() {
+                for (dk, c) in c.iter_mut().enumerate() {
+                    let index = self.perm_x[((i + di as i32) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i32) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i32) & 255) as usize];
+                    *c = self.gradients[index];
+                }
+            }
+        }
+
+        perlin_interp(&c, f)
+    }
+
+    /// Sum of `depth` octaves of noise, each with double frequency and half amplitude
+    pub fn turbulence(&self, point: Vec3, depth: u32) -> Real {
+        let mut accum = 0.0;
+        let mut p = point;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(p);
+            weight *= 0.5;
+            p *= 2.0;
+        }
+
+        accum.abs()
+    }
+}
+
+/// Trilinear interpolation of the gradient contributions, smoothed with a Hermite cubic
+fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], f: Vec3) -> Real {
+    let s = f * f * (3.0 - 2.0 * f);
+
+    let mut accum = 0.0;
+    for (i, c) in c.iter().enumerate() {
+        for (j, c) in c.iter().enumerate() {
+            for (k, gradient) in c.iter().enumerate() {
+                let [i, j, k] = [i as Real, j as Real, k as Real];
+                let weight = f - Vec3::new(i, j, k);
+                accum += (i * s.x + (1.0 - i) * (1.0 - s.x))
+                    * (j * s.y + (1.0 - j) * (1.0 - s.y))
+                    * (k * s.z + (1.0 - k) * (1.0 - s.z))
+                    * gradient.dot(weight);
+            }
+        }
+    }
+    accum
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_perlin_seeded() {
+        let a = Perlin::new(42);
+        let b = Perlin::new(42);
+        let c = Perlin::new(7);
+
+        let points =
+            (0..100).map(|i| Vec3::new(i as Real * 0.37, i as Real * 0.11, -(i as Real) * 0.53));
+        let mut differs = false;
+        for p in points {
+            let noise = a.noise(p);
+            assert_eq!(noise, b.noise(p));
+            assert!((-1.0..=1.0).contains(&noise));
+            differs |= noise != c.noise(p);
+        }
+        assert!(differs);
+
+        // gradient noise vanishes on the lattice points
+        assert!(a.noise(Vec3::new(3.0, -2.0, 5.0)).abs() < 1e-6);
+    }
+}
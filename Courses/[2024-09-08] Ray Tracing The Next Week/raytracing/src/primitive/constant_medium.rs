@@ -0,0 +1,86 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    material::{Isotropic, Material},
+    math::{Real, Vec3},
+    texture::Texture,
+    utils::splitmix64,
+    world::bvh::{Aabb, AabbHittable, HasAabb},
+    HitRecord, Hittable, MaterialRef, Ray,
+};
+
+/// A volume of constant density (smoke, fog) filling a convex `boundary`
+pub struct ConstantMedium {
+    boundary: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
+    neg_inv_density: Real,
+    phase_function: Arc<Box<dyn Material + Send + Sync>>,
+}
+
+impl ConstantMedium {
+    pub fn new(
+        boundary: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
+        density: Real,
+        texture: Arc<Box<dyn Texture + Send + Sync>>,
+    ) -> Self {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Box::new(Isotropic::new(texture))),
+        }
+    }
+}
+
+impl Hittable<MaterialRef> for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        // where the ray enters and leaves the boundary, even if that is behind the origin
+        let enter = self.boundary.hit(ray, Real::NEG_INFINITY..Real::INFINITY)?;
+        let exit = self.boundary.hit(ray, enter.t + 0.0001..Real::INFINITY)?;
+
+        let t_enter = enter.t.max(t_range.start).max(0.0);
+        let t_exit = exit.t.min(t_range.end);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        // sample the free-flight distance, the ray passes through if it is beyond the exit
+        let ray_length = ray.direction.length();
+        let distance_inside = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * random_from_ray(ray).ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+        Some(HitRecord {
+            t,
+            point: ray.at(t),
+            normal: Vec3::X,  // arbitrary
+            front_face: true, // arbitrary
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            footprint: 0.0,
+            material: Some(self.phase_function.clone()),
+        })
+    }
+}
+
+/// A random number in `(0, 1]` derived from the ray, as [`Hittable::hit`] has no random number
+/// generator to draw from. Seeded renders stay reproducible because the rays are.
+// the bits are already `u64` with `f64`
+#[cfg_attr(feature = "f64", allow(clippy::unnecessary_cast))]
+fn random_from_ray(ray: &Ray) -> Real {
+    let bits = [ray.origin, ray.direction]
+        .into_iter()
+        .flat_map(|v| v.to_array())
+        .chain([ray.time]);
+    let hash = bits.fold(0, |state, x| splitmix64(state ^ x.to_bits() as u64));
+    1.0 - (hash >> 40) as Real / (1u64 << 24) as Real
+}
+
+impl HasAabb for ConstantMedium {
+    fn aabb(&self) -> Aabb {
+        self.boundary.aabb()
+    }
+}
@@ -0,0 +1,99 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    material::Material,
+    math::{Real, Vec3},
+    primitive::Quad,
+    world::bvh::{Aabb, HasAabb},
+    HitRecord, Hittable, MaterialRef, Ray,
+};
+
+/// An axis-aligned box made of 6 [`Quad`]s
+///
+/// Rotate or move it with the wrappers in [`crate::world::instance`].
+pub struct Cuboid {
+    sides: [Quad; 6],
+
+    /// cached value
+    aabb: Aabb,
+}
+
+impl Cuboid {
+    /// Creates the box spanned by the two opposite vertices `a` and `b`
+    pub fn new(a: Vec3, b: Vec3, material: Arc<Box<dyn Material + Send + Sync>>) -> Self {
+        let min = a.min(b);
+        let max = a.max(b);
+
+        let dx = Vec3::new(max.x - min.x, 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y - min.y, 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z - min.z);
+
+        let sides = [
+            Quad::new(Vec3::new(min.x, min.y, max.z), dx, dy, material.clone()), // front
+            Quad::new(Vec3::new(max.x, min.y, max.z), -dz, dy, material.clone()), // right
+            Quad::new(Vec3::new(max.x, min.y, min.z), -dx, dy, material.clone()), // back
+            Quad::new(Vec3::new(min.x, min.y, min.z), dz, dy, material.clone()), // left
+            Quad::new(Vec3::new(min.x, max.y, max.z), dx, -dz, material.clone()), // top
+            Quad::new(Vec3::new(min.x, min.y, min.z), dx, dz, material),         // bottom
+        ];
+
+        Cuboid {
+            sides,
+            aabb: Aabb::new(min, max),
+        }
+    }
+}
+
+impl Hittable<MaterialRef> for Cuboid {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        let mut closest = t_range.end;
+        let mut hit_record = None;
+        for side in self.sides.iter() {
+            if let Some(record) = side.hit(ray, t_range.start..closest) {
+                closest = record.t;
+                hit_record = Some(record);
+            }
+        }
+        hit_record
+    }
+}
+
+impl HasAabb for Cuboid {
+    fn aabb(&self) -> Aabb {
+        self.aabb.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{material::Lambertian, texture::SolidColor};
+
+    use super::*;
+
+    #[test]
+    fn test_cuboid_hit() {
+        let cuboid = Cuboid::new(
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(-1.0, -1.0, -1.0),
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::ONE),
+            ))))),
+        );
+        assert!(cuboid.aabb().min().abs_diff_eq(Vec3::NEG_ONE, 1e-4));
+        assert!(cuboid.aabb().max().abs_diff_eq(Vec3::ONE, 1e-4));
+
+        // from outside, the closest side is hit
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::NEG_Z);
+        let record = cuboid.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((record.t - 4.0).abs() < 1e-4);
+        assert!(record.front_face);
+        assert!(record.normal.abs_diff_eq(Vec3::Z, 1e-4));
+
+        // from inside, the back face is hit and the normal faces the ray
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        let record = cuboid.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((record.t - 1.0).abs() < 1e-4);
+        assert!(!record.front_face);
+        assert!(record.normal.abs_diff_eq(Vec3::NEG_X, 1e-4));
+    }
+}
@@ -0,0 +1,66 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    material::Material,
+    math::{Real, Vec2, Vec3},
+    primitive::Triangle,
+    world::bvh::{Aabb, AabbHittable, Bvh, HasAabb},
+    HitRecord, Hittable, MaterialRef, Ray,
+};
+
+/// An indexed triangle mesh with its own [`Bvh`] over the triangles
+pub struct TriangleMesh {
+    bvh: Bvh,
+    triangle_count: usize,
+}
+
+impl TriangleMesh {
+    /// `indices` index into `positions`, and into `normals`/`uvs` when they are
+    /// given, so they must have the same length as `positions`.
+    ///
+    /// Panics if `indices` is empty.
+    pub fn new(
+        positions: &[Vec3],
+        normals: Option<&[Vec3]>,
+        uvs: Option<&[Vec2]>,
+        indices: &[[u32; 3]],
+        material: Arc<Box<dyn Material + Send + Sync>>,
+    ) -> Self {
+        let triangles = indices
+            .iter()
+            .map(|&[a, b, c]| {
+                let [a, b, c] = [a as usize, b as usize, c as usize];
+                let mut triangle =
+                    Triangle::new(positions[a], positions[b], positions[c], material.clone());
+                if let Some(normals) = normals {
+                    triangle = triangle.normals([normals[a], normals[b], normals[c]]);
+                }
+                if let Some(uvs) = uvs {
+                    triangle = triangle.uvs([uvs[a], uvs[b], uvs[c]]);
+                }
+                Box::new(triangle) as Box<dyn AabbHittable<MaterialRef> + Send + Sync>
+            })
+            .collect::<Vec<_>>();
+
+        TriangleMesh {
+            triangle_count: triangles.len(),
+            bvh: Bvh::from_objects(triangles),
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+}
+
+impl Hittable<MaterialRef> for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_range)
+    }
+}
+
+impl HasAabb for TriangleMesh {
+    fn aabb(&self) -> Aabb {
+        self.bvh.aabb()
+    }
+}
@@ -1,5 +1,13 @@
 pub mod sphere;
 pub mod quad;
+pub mod cuboid;
+pub mod triangle;
+pub mod mesh;
+pub mod constant_medium;
 
 pub use sphere::Sphere;
 pub use quad::Quad;
+pub use cuboid::Cuboid;
+pub use triangle::Triangle;
+pub use mesh::TriangleMesh;
+pub use constant_medium::ConstantMedium;
\ No newline at end of file
@@ -1,11 +1,15 @@
-use std::sync::Arc;
+use std::{
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 
-use glam::Vec3;
+use rand::{Rng, RngCore};
 
 use crate::{
     material::Material,
+    math::{Real, Vec3},
     world::bvh::{Aabb, HasAabb},
-    HitRecord, Hittable,
+    HitRecord, Hittable, MaterialRef, Sampleable,
 };
 
 pub struct Quad {
@@ -17,6 +21,7 @@ pub struct Quad {
     /// followings are cached values
     normal: Vec3, // normalized
     w: Vec3,
+    area: Real,
 }
 
 impl Quad {
@@ -25,14 +30,15 @@ impl Quad {
         
         let w = n / n.dot(n);
         let normal = n.normalize();
-        Quad { q, u, v, material, normal, w }
+        let area = n.length();
+        Quad { q, u, v, material, normal, w, area }
     }
 }
 
-impl Hittable for Quad {
-    fn hit(&self, ray: &crate::Ray, t_range: std::ops::Range<f32>) -> Option<crate::HitRecord> {
+impl Hittable<MaterialRef> for Quad {
+    fn hit(&self, ray: &crate::Ray, t_range: std::ops::Range<Real>) -> Option<crate::HitRecord> {
         let denom = self.normal.dot(ray.direction);
-        if denom.abs() < f32::EPSILON {
+        if denom.abs() < Real::EPSILON {
             return None;
         }
 
@@ -46,24 +52,59 @@ impl Hittable for Quad {
         let point_on_plane = point - self.q;
         let u = self.w.dot(point_on_plane.cross(self.v));
         let v = self.w.dot(self.u.cross(point_on_plane));
-        if !(0.0 <= u && u <= 1.0 && 0.0 <= v && v <= 1.0) {
+        if !((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)) {
             return None;
         }
 
-        let front_face = self.normal.dot(ray.direction) > 0.0;
+        // keep the normal against the ray like `Sphere` does, so that closed
+        // rooms built from quads scatter back inside instead of through the walls
+        let front_face = self.normal.dot(ray.direction) < 0.0;
+        let normal = if front_face {
+            self.normal
+        } else {
+            -self.normal
+        };
 
         Some(HitRecord {
             t,
             point,
-            normal: self.normal,
+            normal,
             front_face,
             u,
             v,
+            tangent: self.u.normalize(),
+            bitangent: self.v.normalize(),
+            footprint: ray.width_at(t) / self.u.length().min(self.v.length()),
             material: Some(self.material.clone()),
         })
     }
 }
 
+impl Sampleable for Quad {
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, _time: Real) -> Real {
+        let Some(record) = self.hit(&crate::Ray::new(origin, direction), 0.001..Real::INFINITY)
+        else {
+            return 0.0;
+        };
+
+        // convert the uniform density over the area to a density over solid angle
+        let distance_squared = record.t * record.t * direction.length_squared();
+        let cosine = (direction.dot(record.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Vec3, _time: Real, rng: &mut dyn RngCore) -> Vec3 {
+        let point = self.q + rng.gen::<Real>() * self.u + rng.gen::<Real>() * self.v;
+        point - origin
+    }
+
+    fn hash_settings(&self, mut state: &mut dyn Hasher) {
+        [self.q, self.u, self.v]
+            .map(|v| v.to_array().map(Real::to_bits))
+            .hash(&mut state);
+    }
+}
+
 impl HasAabb for Quad {
     fn aabb(&self) -> Aabb {
         Aabb::new(self.q, self.q + self.u + self.v)
@@ -90,20 +131,26 @@ mod test {
 
         println!("{:?}", quad.aabb());
         let ray = Ray::new(Vec3::ZERO, Vec3::Z);
-        let hit = quad.aabb().hit(&ray, 0.0..f32::INFINITY);
-        assert!(hit.is_none());
+        let hit = quad
+            .aabb()
+            .intersects(&ray, ray.inv_direction(), 0.0..Real::INFINITY);
+        assert!(hit.is_some());
 
         let ray = Ray::new(Vec3::ZERO, Vec3::Z);
         let hit = quad.hit(&ray, 0.0..1.0);
         assert!(hit.is_some());
-        let ray = Ray::new(Vec3::ZERO, Vec3::new(2.0, 0.0, 1.0));
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0));
         let hit = quad.hit(&ray, 0.0..1.0);
         assert!(hit.is_some());
-        let ray = Ray::new(Vec3::ZERO, Vec3::new(0.0, 2.0, 1.0));
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 1.0));
         let hit = quad.hit(&ray, 0.0..1.0);
         assert!(hit.is_some());
-        let ray = Ray::new(Vec3::ZERO, Vec3::new(2.0, 2.0, 1.0));
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(1.0, 1.0, 1.0));
         let hit = quad.hit(&ray, 0.0..1.0);
         assert!(hit.is_some());
+        // a cone 0.02 wide where it meets the quad, a hundredth of its width
+        let ray = Ray::new(Vec3::ZERO, Vec3::Z * 0.5).with_spread(0.02);
+        let record = quad.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((record.footprint - 0.01).abs() < 1e-6);
     }
 }
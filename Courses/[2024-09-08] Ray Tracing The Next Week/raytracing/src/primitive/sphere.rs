@@ -1,40 +1,91 @@
-use std::{f32::consts::PI, ops::Range, sync::Arc};
-use glam::Vec3;
+use std::{
+    hash::{Hash, Hasher},
+    ops::Range,
+    sync::Arc,
+};
+use rand::{Rng, RngCore};
 
 use crate::material::Material;
+use crate::utils::Onb;
 use crate::world::bvh::{Aabb, HasAabb};
 use crate::Ray;
 
-use crate::{HitRecord, Hittable};
+use crate::{
+    math::{consts::PI, Real, Vec3},
+    HitRecord, Hittable, MaterialRef, Sampleable,
+};
 
 #[derive(Clone)]
 pub struct Sphere {
+    /// center at time 0
     center: Vec3,
-    radius: f32,
+    /// how far the center moves from time 0 to time 1
+    motion: Vec3,
+    radius: Real,
     material: Arc<Box<dyn Material + Send + Sync>>,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32, material: Arc<Box<dyn Material + Send + Sync>>) -> Self {
+    pub fn new(center: Vec3, radius: Real, material: Arc<Box<dyn Material + Send + Sync>>) -> Self {
         Sphere {
             center,
+            motion: Vec3::ZERO,
             radius,
             material,
         }
     }
 
-    pub fn get_uv(&self, point: Vec3) -> (f32, f32) {
+    /// A sphere moving linearly from `center0` at time 0 to `center1` at time 1
+    pub fn moving(
+        center0: Vec3,
+        center1: Vec3,
+        radius: Real,
+        material: Arc<Box<dyn Material + Send + Sync>>,
+    ) -> Self {
+        Sphere {
+            center: center0,
+            motion: center1 - center0,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center_at(&self, time: Real) -> Vec3 {
+        self.center + time * self.motion
+    }
+
+    /// Longitude and latitude of a `point` on the unit sphere, both in `[0, 1]`
+    pub fn get_uv(point: Vec3) -> (Real, Real) {
         let theta = (-point.y).acos();
         let phi = point.z.atan2(-point.x) + PI;
         let u = phi / (2.0 * PI);
         let v = theta / PI;
         (u, v)
     }
+
+    /// The point of the unit sphere at `(u, v)`, the inverse of [`Sphere::get_uv`]
+    pub fn uv_point(u: Real, v: Real) -> Vec3 {
+        let theta = v * PI;
+        let phi = u * 2.0 * PI;
+        Vec3::new(
+            theta.sin() * phi.cos(),
+            -theta.cos(),
+            -theta.sin() * phi.sin(),
+        )
+    }
+
+    /// Directions of growing `u` (around the y axis) and `v` (from the bottom to the top) at
+    /// `normal`, both zero at the poles
+    fn tangents(normal: Vec3) -> (Vec3, Vec3) {
+        let tangent = Vec3::new(normal.z, 0.0, -normal.x).normalize_or_zero();
+        (tangent, normal.cross(tangent))
+    }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
-        let oc = ray.origin - self.center;
+impl Hittable<MaterialRef> for Sphere {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        let center = self.center_at(ray.time);
+        let oc = ray.origin - center;
         let a = ray.direction.dot(ray.direction);
         let b = oc.dot(ray.direction);
         let c = oc.dot(oc) - self.radius * self.radius;
@@ -53,12 +104,18 @@ impl Hittable for Sphere {
         }
 
         let point = ray.at(t);
-        let normal = (point - self.center) / self.radius;
+        let outward_normal = (point - center) / self.radius;
 
-        let front_face = ray.direction.dot(normal) < 0.0;
-        let normal = if front_face { normal } else { -normal };
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        let normal = if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
 
-        let (u, v) = self.get_uv(normal);
+        // the same texel and tangents from either side of the surface
+        let (u, v) = Self::get_uv(outward_normal);
+        let (tangent, bitangent) = Self::tangents(outward_normal);
 
         Some(HitRecord {
             point,
@@ -68,15 +125,198 @@ impl Hittable for Sphere {
             material: Some(self.material.clone()),
             u,
             v,
+            tangent,
+            bitangent,
+            // v runs over half a circumference, the denser of u and v
+            footprint: ray.width_at(t) / (PI * self.radius),
         })
     }
 }
 
+/// A moving sphere is sampled where it is at the time of the ray
+impl Sampleable for Sphere {
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, time: Real) -> Real {
+        let ray = Ray::new(origin, direction).with_time(time);
+        if self.hit(&ray, 0.001..Real::INFINITY).is_none() {
+            return 0.0;
+        }
+
+        // uniform over the cone of directions that see the sphere
+        let distance_squared = (self.center_at(time) - origin).length_squared();
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared)
+            .max(0.0)
+            .sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Vec3, time: Real, rng: &mut dyn RngCore) -> Vec3 {
+        let direction = self.center_at(time) - origin;
+        let distance_squared = direction.length_squared();
+        let uvw = Onb::new(direction);
+
+        let r1 = rng.gen::<Real>();
+        let r2 = rng.gen::<Real>();
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared)
+            .max(0.0)
+            .sqrt();
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * r1;
+        let x = phi.cos() * (1.0 - z * z).sqrt();
+        let y = phi.sin() * (1.0 - z * z).sqrt();
+        uvw.transform(Vec3::new(x, y, z))
+    }
+
+    fn hash_settings(&self, mut state: &mut dyn Hasher) {
+        [self.center, self.motion]
+            .map(|v| v.to_array().map(Real::to_bits))
+            .hash(&mut state);
+        self.radius.to_bits().hash(&mut state);
+    }
+}
+
 impl HasAabb for Sphere {
+    /// Covers the whole path of a moving sphere
     fn aabb(&self) -> Aabb {
-        Aabb::new(
-            self.center - Vec3::splat(self.radius),
-            self.center + Vec3::splat(self.radius),
-        )
+        let r = Vec3::splat(self.radius);
+        let center1 = self.center + self.motion;
+        Aabb::new(self.center - r, self.center + r).union(&Aabb::new(center1 - r, center1 + r))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use crate::{material::Lambertian, texture::SolidColor, utils::random_in_unit_sphere};
+
+    use super::*;
+
+    #[test]
+    fn test_sphere_tangents() {
+        let sphere = Sphere::new(
+            Vec3::ZERO,
+            2.0,
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::ONE),
+            ))))),
+        );
+        let uv = |point: Vec3| Sphere::get_uv(point.normalize());
+
+        for direction in [Vec3::new(1.0, 0.5, 2.0), Vec3::new(-3.0, -1.0, 0.5)] {
+            let ray = Ray::new(2.0 * direction, -direction);
+            let record = sphere.hit(&ray, 0.0..Real::INFINITY).unwrap();
+            assert!(record.tangent.dot(record.normal).abs() < 1e-5);
+            assert!(record.bitangent.dot(record.normal).abs() < 1e-5);
+
+            // moving along them makes u and v grow
+            let (u, v) = uv(record.point);
+            let (u_t, v_t) = uv(record.point + 1e-2 * record.tangent);
+            assert!(u_t > u && (v_t - v).abs() < 1e-4);
+            let (u_b, v_b) = uv(record.point + 1e-2 * record.bitangent);
+            assert!(v_b > v && (u_b - u).abs() < 1e-4);
+        }
+
+        // a back face has the uv of the point that was hit, not of the opposite one
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(1.0, 0.5, 2.0));
+        let record = sphere.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!(!record.front_face);
+        let (u, v) = uv(record.point);
+        assert!((record.u - u).abs() < 1e-5 && (record.v - v).abs() < 1e-5);
+        let (u_t, _) = uv(record.point + 1e-2 * record.tangent);
+        assert!(u_t > u);
+    }
+
+    /// Fraction of the rays bouncing off a sphere of `radius` that hit it again right away,
+    /// i.e. whose hit point sank below the surface. That is what shows up as shadow acne.
+    fn acne(radius: Real, t_min: Real) -> f32 {
+        let center = Vec3::new(0.0, -radius, 0.0);
+        let sphere = Sphere::new(
+            center,
+            radius,
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::ONE),
+            ))))),
+        );
+        let origin = Vec3::new(13.0, 2.0, 3.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let (mut hits, mut acne) = (0, 0);
+        for _ in 0..10000 {
+            let target =
+                center + radius * (Vec3::Y + 0.1 * random_in_unit_sphere(&mut rng)).normalize();
+            let ray = Ray::new(origin, target - origin);
+            // with `f32` even some of these miss a huge sphere
+            let Some(record) = sphere.hit(&ray, t_min..Real::INFINITY) else {
+                continue;
+            };
+            hits += 1;
+
+            let bounce = Ray::new(
+                record.point,
+                record.normal + random_in_unit_sphere(&mut rng),
+            );
+            if sphere.hit(&bounce, t_min..Real::INFINITY).is_some() {
+                acne += 1;
+            }
+        }
+        acne as f32 / hits as f32
+    }
+
+    /// Run with and without the `f64` feature to compare the two
+    #[test]
+    fn test_acne() {
+        // ignoring hits closer than 0.001 hides the acne of small spheres either way
+        assert_eq!(acne(1.0, 0.001), 0.0);
+        assert_eq!(acne(100.0, 0.001), 0.0);
+
+        // but not on the ground of a scene, unless it is computed in `f64`
+        #[cfg(not(feature = "f64"))]
+        assert!(acne(1e5, 0.001) > 0.1);
+        #[cfg(feature = "f64")]
+        {
+            assert_eq!(acne(1e5, 0.001), 0.0);
+            assert_eq!(acne(1e5, 1e-6), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_uv_point() {
+        for point in [Vec3::X, Vec3::NEG_Z, Vec3::new(0.3, -0.8, 0.5).normalize()] {
+            let (u, v) = Sphere::get_uv(point);
+            assert!(Sphere::uv_point(u, v).abs_diff_eq(point, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_moving_sphere() {
+        let sphere = Sphere::moving(
+            Vec3::ZERO,
+            Vec3::new(0.0, 2.0, 0.0),
+            0.5,
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::ONE),
+            ))))),
+        );
+        assert!(sphere.aabb().min().abs_diff_eq(Vec3::splat(-0.5), 1e-4));
+        assert!(sphere
+            .aabb()
+            .max()
+            .abs_diff_eq(Vec3::new(0.5, 2.5, 0.5), 1e-4));
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        assert!(sphere.hit(&ray, 0.0..Real::INFINITY).is_some());
+        let ray = ray.with_time(1.0);
+        assert!(sphere.hit(&ray, 0.0..Real::INFINITY).is_none());
+
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 5.0), Vec3::NEG_Z).with_time(0.5);
+        let record = sphere.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!(record.point.abs_diff_eq(Vec3::new(0.0, 1.0, 0.5), 1e-4));
+
+        // as a light it is sampled where it is at the time of the ray
+        let (origin, rng) = (Vec3::new(0.0, 2.0, 5.0), &mut SmallRng::seed_from_u64(0));
+        for _ in 0..100 {
+            let direction = sphere.random(origin, 1.0, rng);
+            assert!(sphere.pdf_value(origin, direction, 1.0) > 0.0);
+            assert_eq!(sphere.pdf_value(origin, direction, 0.0), 0.0);
+        }
+    }
+}
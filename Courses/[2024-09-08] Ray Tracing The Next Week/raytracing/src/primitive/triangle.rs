@@ -0,0 +1,174 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    material::Material,
+    math::{Real, Vec2, Vec3},
+    world::bvh::{Aabb, HasAabb},
+    HitRecord, Hittable, MaterialRef, Ray,
+};
+
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    /// per-vertex normals, interpolated for smooth shading
+    normals: Option<[Vec3; 3]>,
+    /// per-vertex texture coordinates
+    uvs: Option<[Vec2; 3]>,
+    material: Arc<Box<dyn Material + Send + Sync>>,
+
+    /// followings are cached values
+    e1: Vec3,
+    e2: Vec3,
+    normal: Vec3, // normalized
+    /// normalized directions of growing u and v
+    tangents: (Vec3, Vec3),
+    /// uv units per world unit
+    uv_scale: Real,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Arc<Box<dyn Material + Send + Sync>>) -> Self {
+        let e1 = b - a;
+        let e2 = c - a;
+        let normal = e1.cross(e2).normalize();
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            material,
+            e1,
+            e2,
+            normal,
+            // the barycentric coordinates b1 and b2 stand in for u and v
+            tangents: (e1.normalize(), e2.normalize()),
+            uv_scale: e1.cross(e2).length().recip().sqrt(),
+        }
+    }
+
+    pub fn normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals.map(|n| n.normalize()));
+        self
+    }
+
+    pub fn uvs(mut self, uvs: [Vec2; 3]) -> Self {
+        self.uvs = Some(uvs);
+
+        // solves e1 = dp/du * duv1.x + dp/dv * duv1.y, and the same for e2
+        let (duv1, duv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+        let det = duv1.perp_dot(duv2);
+        if det.abs() > Real::EPSILON {
+            let tangent = (self.e1 * duv2.y - self.e2 * duv1.y) / det;
+            let bitangent = (self.e2 * duv1.x - self.e1 * duv2.x) / det;
+            self.tangents = (tangent.normalize(), bitangent.normalize());
+            self.uv_scale = (det.abs() / self.e1.cross(self.e2).length()).sqrt();
+        }
+        self
+    }
+}
+
+impl Hittable<MaterialRef> for Triangle {
+    /// Möller–Trumbore intersection
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        let p = ray.direction.cross(self.e2);
+        let det = self.e1.dot(p);
+        if det.abs() < Real::EPSILON {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let s = ray.origin - self.vertices[0];
+        let b1 = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = s.cross(self.e1);
+        let b2 = ray.direction.dot(q) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = self.e2.dot(q) * inv_det;
+        if t < t_range.start || t > t_range.end {
+            return None;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+        let front_face = self.normal.dot(ray.direction) < 0.0;
+        let normal = match self.normals {
+            Some([n0, n1, n2]) => (b0 * n0 + b1 * n1 + b2 * n2).normalize(),
+            None => self.normal,
+        };
+        let normal = if front_face { normal } else { -normal };
+
+        // without texture coordinates the barycentric coordinates are used as uv
+        let (u, v) = match self.uvs {
+            Some([uv0, uv1, uv2]) => (b0 * uv0 + b1 * uv1 + b2 * uv2).into(),
+            None => (b1, b2),
+        };
+
+        Some(HitRecord {
+            t,
+            point: ray.at(t),
+            normal,
+            front_face,
+            u,
+            v,
+            tangent: self.tangents.0,
+            bitangent: self.tangents.1,
+            footprint: ray.width_at(t) * self.uv_scale,
+            material: Some(self.material.clone()),
+        })
+    }
+}
+
+impl HasAabb for Triangle {
+    fn aabb(&self) -> Aabb {
+        Aabb::from_points(self.vertices)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{material::Lambertian, texture::SolidColor};
+
+    use super::*;
+
+    #[test]
+    fn test_triangle_hit() {
+        let triangle = Triangle::new(
+            Vec3::ZERO,
+            Vec3::X,
+            Vec3::Y,
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::ONE),
+            ))))),
+        );
+
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 1.0), Vec3::NEG_Z);
+        let record = triangle.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((record.t - 1.0).abs() < 1e-5);
+        assert!((record.u - 0.25).abs() < 1e-5);
+        assert!((record.v - 0.5).abs() < 1e-5);
+        assert!(record.front_face);
+        assert!(record.normal.abs_diff_eq(Vec3::Z, 1e-5));
+
+        let ray = Ray::new(Vec3::new(0.6, 0.6, 1.0), Vec3::NEG_Z);
+        assert!(triangle.hit(&ray, 0.0..Real::INFINITY).is_none());
+
+        let triangle = triangle.uvs([Vec2::ZERO, Vec2::new(2.0, 0.0), Vec2::new(0.0, 2.0)]);
+        let ray = Ray::new(Vec3::new(0.25, 0.5, -1.0), Vec3::Z);
+        let record = triangle.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((record.u - 0.5).abs() < 1e-5);
+        assert!((record.v - 1.0).abs() < 1e-5);
+        assert!(!record.front_face);
+        assert!(record.normal.abs_diff_eq(Vec3::NEG_Z, 1e-5));
+        assert!(record.tangent.abs_diff_eq(Vec3::X, 1e-5));
+        assert!(record.bitangent.abs_diff_eq(Vec3::Y, 1e-5));
+
+        // u along y and v along x
+        let triangle = triangle.uvs([Vec2::ZERO, Vec2::Y, Vec2::X]);
+        let record = triangle.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!(record.tangent.abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(record.bitangent.abs_diff_eq(Vec3::X, 1e-5));
+    }
+}
@@ -0,0 +1,279 @@
+//! Progressive rendering, see [`Camera::render_progressive`](crate::camera::Camera::render_progressive)
+//!
+//! Sample passes are summed up in an [`Accumulator`], which can be saved as a checkpoint and
+//! resumed by a later run.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use image::{Rgb, Rgb32FImage};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use crate::math::{from_f32_array, to_f32_array, Real, Vec3};
+
+const MAGIC: &[u8; 4] = b"RTCK";
+const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// Not a checkpoint file, or written by an incompatible version
+    InvalidFormat,
+    /// The checkpoint was rendered from another scene, camera or resolution
+    Mismatch,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "checkpoint io error: {err}"),
+            CheckpointError::InvalidFormat => write!(f, "invalid checkpoint file"),
+            CheckpointError::Mismatch => {
+                write!(
+                    f,
+                    "checkpoint belongs to another scene, camera or resolution"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CheckpointError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => CheckpointError::InvalidFormat,
+            _ => CheckpointError::Io(err),
+        }
+    }
+}
+
+/// Per-pixel sums of the radiance samples taken so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accumulator {
+    width: u32,
+    height: u32,
+    /// identifies the scene and camera the samples come from
+    scene_hash: u64,
+    /// samples per pixel so far
+    samples: u32,
+    passes: u32,
+    /// row major
+    sums: Vec<Vec3>,
+}
+
+impl Accumulator {
+    pub fn new(width: u32, height: u32, scene_hash: u64) -> Self {
+        Accumulator {
+            width,
+            height,
+            scene_hash,
+            samples: 0,
+            passes: 0,
+            sums: vec![Vec3::ZERO; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn scene_hash(&self) -> u64 {
+        self.scene_hash
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// Adds `samples` samples to every pixel, `sample_sum(x, y)` returns their sum
+    pub fn add_pass<F>(&mut self, samples: u32, sample_sum: F)
+    where
+        F: Fn(u32, u32) -> Vec3 + Sync,
+    {
+        let width = self.width;
+        self.sums.par_iter_mut().enumerate().for_each(|(i, sum)| {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            *sum += sample_sum(x, y);
+        });
+        self.samples += samples;
+        self.passes += 1;
+    }
+
+    /// The mean radiance of every pixel
+    pub fn image(&self) -> Rgb32FImage {
+        let samples = self.samples.max(1) as Real;
+        Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            let sum = self.sums[(y * self.width + x) as usize];
+            Rgb(to_f32_array(sum / samples))
+        })
+    }
+
+    /// Writes a checkpoint to `path`, through a temporary file so that a killed run never
+    /// leaves a truncated checkpoint behind
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&self.scene_hash.to_le_bytes())?;
+        for value in [self.width, self.height, self.samples, self.passes] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for value in self.sums.iter().flat_map(|&sum| to_f32_array(sum)) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut reader)? != VERSION {
+            return Err(CheckpointError::InvalidFormat);
+        }
+        let mut scene_hash = [0; 8];
+        reader.read_exact(&mut scene_hash)?;
+        let scene_hash = u64::from_le_bytes(scene_hash);
+        let width = read_u32(&mut reader)?;
+        let height = read_u32(&mut reader)?;
+        let samples = read_u32(&mut reader)?;
+        let passes = read_u32(&mut reader)?;
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.len() != width as usize * height as usize * 3 * 4 {
+            return Err(CheckpointError::InvalidFormat);
+        }
+        let sums = data
+            .chunks_exact(12)
+            .map(|chunk| {
+                let [x, y, z] = [0, 4, 8].map(|i| {
+                    f32::from_le_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]])
+                });
+                from_f32_array([x, y, z])
+            })
+            .collect();
+
+        Ok(Accumulator {
+            width,
+            height,
+            scene_hash,
+            samples,
+            passes,
+            sums,
+        })
+    }
+
+    /// Loads a checkpoint, failing with [`CheckpointError::Mismatch`] if it was not rendered
+    /// at this resolution from the scene of `scene_hash`
+    pub fn resume(
+        path: impl AsRef<Path>,
+        width: u32,
+        height: u32,
+        scene_hash: u64,
+    ) -> Result<Self, CheckpointError> {
+        let accumulator = Accumulator::load(path)?;
+        if accumulator.width != width
+            || accumulator.height != height
+            || accumulator.scene_hash != scene_hash
+        {
+            return Err(CheckpointError::Mismatch);
+        }
+        Ok(accumulator)
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{background::Color, camera::Camera, world::list::List};
+
+    use super::*;
+
+    #[test]
+    fn test_progressive_checkpoint() {
+        let world = List::from_objects(Vec::new());
+        let camera = Camera::new(2.0)
+            .samples_per_pixel(10)
+            .background(Arc::new(Box::new(Color(Vec3::new(0.25, 0.5, 4.0)))));
+
+        let mut accumulator = Accumulator::new(8, 4, 42);
+        let mut passes = Vec::new();
+        camera.render_progressive(&world, &mut accumulator, 4, |accumulator| {
+            passes.push(accumulator.samples())
+        });
+        assert_eq!(passes, [4, 8, 10]);
+        assert_eq!(accumulator.passes(), 3);
+        for pixel in accumulator.image().pixels() {
+            assert!(from_f32_array(pixel.0).abs_diff_eq(Vec3::new(0.25, 0.5, 4.0), 1e-5));
+        }
+
+        let dir =
+            std::env::temp_dir().join(format!("raytracing-test-checkpoint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("checkpoint.bin");
+        accumulator.save(&path).unwrap();
+        assert_eq!(Accumulator::resume(&path, 8, 4, 42).unwrap(), accumulator);
+        assert!(matches!(
+            Accumulator::resume(&path, 8, 4, 7),
+            Err(CheckpointError::Mismatch)
+        ));
+
+        // resuming with more samples only renders the missing ones
+        let mut accumulator = Accumulator::load(&path).unwrap();
+        let camera = camera.samples_per_pixel(16);
+        let mut passes = Vec::new();
+        camera.render_progressive(&world, &mut accumulator, 4, |accumulator| {
+            passes.push(accumulator.samples())
+        });
+        assert_eq!(passes, [14, 16]);
+        assert_eq!(accumulator.passes(), 5);
+
+        std::fs::write(&path, b"RTCK").unwrap();
+        assert!(matches!(
+            Accumulator::load(&path),
+            Err(CheckpointError::InvalidFormat)
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
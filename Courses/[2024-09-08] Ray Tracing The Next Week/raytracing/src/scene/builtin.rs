@@ -0,0 +1,462 @@
+//! The scenes of the book, each with the camera it is meant to be viewed from
+
+use std::{path::PathBuf, sync::Arc};
+
+use rand::{rngs::StdRng, SeedableRng};
+use rt_core::scene::{final_scene, MaterialDesc};
+
+use crate::{
+    background::Color,
+    camera::Camera,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    math::Vec3,
+    primitive::{ConstantMedium, Cuboid, Quad, Sphere},
+    texture::{
+        ColorSpace, Filter, ImageTexture, NoisePattern, NoiseTexture, SolidCheckerTexture,
+        SolidColor, Texture,
+    },
+    world::{
+        bvh::{AabbHittable, Bvh},
+        instance::{RotateY, Translate},
+    },
+    MaterialRef, Sampleable,
+};
+
+use super::{Scene, SceneError};
+
+/// Names accepted by [`by_name`]
+pub const NAMES: &[&str] = &[
+    "world",
+    "checkered-spheres",
+    "perlin-spheres",
+    "quads",
+    "simple-light",
+    "cornell-box",
+    "cornell-smoke",
+];
+
+/// `None` for an unknown name, an error when the scene's assets fail to load
+pub fn by_name(name: &str) -> Option<Result<Scene, SceneError>> {
+    let scene = match name {
+        "world" => world(),
+        "checkered-spheres" => return Some(checkered_spheres()),
+        "perlin-spheres" => perlin_spheres(),
+        "quads" => quads(),
+        "simple-light" => simple_light(),
+        "cornell-box" => cornell_box(),
+        "cornell-smoke" => cornell_smoke(),
+        _ => return None,
+    };
+    Some(Ok(scene))
+}
+
+pub fn quads() -> Scene {
+    let objects: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = vec![
+        Box::new(Quad::new(
+            Vec3::new(-3.0, -2.0, 5.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::new(1.0, 0.2, 0.2)),
+            ))))),
+        )),
+        Box::new(Quad::new(
+            Vec3::new(-2.0, -2.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::new(0.2, 1.0, 0.2)),
+            ))))),
+        )),
+        Box::new(Quad::new(
+            Vec3::new(3.0, -2.0, 1.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::new(0.2, 0.2, 1.0)),
+            ))))),
+        )),
+        Box::new(Quad::new(
+            Vec3::new(-2.0, 3.0, 1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::new(1.0, 0.5, 0.0)),
+            ))))),
+        )),
+        Box::new(Quad::new(
+            Vec3::new(-2.0, -3.0, 5.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::new(0.2, 0.8, 0.8)),
+            ))))),
+        )),
+    ];
+
+    let camera = Camera::new(1.0)
+        .fov(80.0)
+        .pos(Vec3::new(0.0, 0.0, 9.0))
+        .look_at(Vec3::ZERO);
+
+    Scene {
+        camera,
+        world: Bvh::from_objects(objects),
+        width: 600,
+    }
+}
+
+pub fn world() -> Scene {
+    let camera = Camera::new(16.0 / 9.0)
+        .fov(20.0)
+        .pos(Vec3::new(13.0, 2.0, 3.0))
+        .look_at(Vec3::ZERO)
+        .defocus_angle(0.6)
+        .focus_distance(10.0);
+
+    Scene {
+        camera,
+        world: Bvh::from_objects(world_objects()),
+        width: 1280,
+    }
+}
+
+/// The spheres of [`world`]
+pub fn world_objects() -> Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> {
+    // the same spheres every time, so renders can be compared and resumed
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut objects = Vec::new();
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+            SolidCheckerTexture::new(
+                0.5,
+                Arc::new(Box::new(SolidColor::new(Vec3::new(0.2, 0.3, 0.1)))),
+                Arc::new(Box::new(SolidColor::new(Vec3::new(0.9, 0.9, 0.9)))),
+            ),
+        ))))),
+    )));
+
+    let solid = |albedo: Vec3| -> Arc<Box<dyn Texture + Send + Sync>> {
+        Arc::new(Box::new(SolidColor::new(albedo)))
+    };
+    for sphere in final_scene(&mut rng) {
+        let material: Box<dyn Material + Send + Sync> = match sphere.material {
+            MaterialDesc::Lambertian { albedo } => Box::new(Lambertian::new(solid(albedo))),
+            MaterialDesc::Metal { albedo, fuzz } => Box::new(Metal::new(solid(albedo)).fuzz(fuzz)),
+            MaterialDesc::Dielectric { refraction_index } => {
+                Box::new(Dielectric::new(refraction_index))
+            }
+        };
+        objects.push(Box::new(Sphere::new(
+            sphere.center,
+            sphere.radius,
+            Arc::new(material),
+        )));
+    }
+
+    objects
+        .into_iter()
+        .map(|obj| obj as Box<dyn AabbHittable<MaterialRef> + Send + Sync>)
+        .collect()
+}
+
+pub fn checkered_spheres() -> Result<Scene, SceneError> {
+    let mut objects = Vec::new();
+
+    let path = PathBuf::from("assets/earthmap.jpg");
+    let earth_texture = ImageTexture::from_path(&path, Some(ColorSpace::Srgb))
+        .map_err(|source| SceneError::Image { path, source })?
+        .filter(Filter::Trilinear);
+    let earth_texture: Arc<Box<dyn Texture + Send + Sync>> = Arc::new(Box::new(earth_texture));
+    let material: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Lambertian::new(earth_texture)));
+
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, -10.0, 0.0),
+        10.0,
+        material.clone(),
+    )));
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, 10.0, 0.0),
+        10.0,
+        material.clone(),
+    )));
+
+    let objects = objects
+        .into_iter()
+        .map(|obj| obj as Box<dyn AabbHittable<MaterialRef> + Send + Sync>)
+        .collect();
+
+    let camera = Camera::new(16.0 / 9.0)
+        .fov(20.0)
+        .pos(Vec3::new(13.0, 2.0, 3.0))
+        .look_at(Vec3::ZERO);
+
+    Ok(Scene {
+        camera,
+        world: Bvh::from_objects(objects),
+        width: 1280,
+    })
+}
+
+pub fn perlin_spheres() -> Scene {
+    let mut objects: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Vec::new();
+
+    let texture: Arc<Box<dyn Texture + Send + Sync>> = Arc::new(Box::new(
+        NoiseTexture::new(0, 4.0).pattern(NoisePattern::Marble),
+    ));
+    let material: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Lambertian::new(texture)));
+
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        material.clone(),
+    )));
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, 2.0, 0.0),
+        2.0,
+        material.clone(),
+    )));
+
+    let camera = Camera::new(16.0 / 9.0)
+        .fov(20.0)
+        .pos(Vec3::new(13.0, 2.0, 3.0))
+        .look_at(Vec3::ZERO);
+
+    Scene {
+        camera,
+        world: Bvh::from_objects(objects),
+        width: 1280,
+    }
+}
+
+pub fn simple_light() -> Scene {
+    let mut objects: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Vec::new();
+
+    let material: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Lambertian::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(0.5, 0.5, 0.5)))),
+    )));
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        material.clone(),
+    )));
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, 2.0, 0.0),
+        2.0,
+        material.clone(),
+    )));
+
+    let light: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(DiffuseLight::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(4.0, 4.0, 4.0)))),
+    )));
+    objects.push(Box::new(Sphere::new(
+        Vec3::new(0.0, 7.0, 0.0),
+        2.0,
+        light.clone(),
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(3.0, 1.0, -2.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        light.clone(),
+    )));
+
+    let camera = Camera::new(16.0 / 9.0)
+        .fov(20.0)
+        .pos(Vec3::new(26.0, 3.0, 6.0))
+        .look_at(Vec3::new(0.0, 2.0, 0.0))
+        .background(Arc::new(Box::new(Color(Vec3::ZERO))));
+
+    Scene {
+        camera,
+        world: Bvh::from_objects(objects),
+        width: 1280,
+    }
+}
+
+fn cornell_camera() -> Camera {
+    Camera::new(1.0)
+        .samples_per_pixel(200)
+        .fov(40.0)
+        .pos(Vec3::new(278.0, 278.0, -800.0))
+        .look_at(Vec3::new(278.0, 278.0, 0.0))
+        .background(Arc::new(Box::new(Color(Vec3::ZERO))))
+}
+
+pub fn cornell_box() -> Scene {
+    let mut objects: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Vec::new();
+
+    let red: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Lambertian::new(Arc::new(
+        Box::new(SolidColor::new(Vec3::new(0.65, 0.05, 0.05))),
+    ))));
+    let white: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Lambertian::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(0.73, 0.73, 0.73)))),
+    )));
+    let green: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Lambertian::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(0.12, 0.45, 0.15)))),
+    )));
+    let light: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(DiffuseLight::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(15.0, 15.0, 15.0)))),
+    )));
+
+    objects.push(Box::new(Quad::new(
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        green,
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        red,
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        light.clone(),
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(555.0, 555.0, 555.0),
+        Vec3::new(-555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        white.clone(),
+    )));
+
+    let box1: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Arc::new(Box::new(
+        Cuboid::new(Vec3::ZERO, Vec3::new(165.0, 330.0, 165.0), white.clone()),
+    ));
+    let box1: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> =
+        Arc::new(Box::new(RotateY::new(box1, 15.0)));
+    objects.push(Box::new(Translate::new(box1, Vec3::new(265.0, 0.0, 295.0))));
+
+    let box2: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Arc::new(Box::new(
+        Cuboid::new(Vec3::ZERO, Vec3::new(165.0, 165.0, 165.0), white.clone()),
+    ));
+    let box2: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> =
+        Arc::new(Box::new(RotateY::new(box2, -18.0)));
+    objects.push(Box::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0))));
+
+    let lights: Arc<Box<dyn Sampleable + Send + Sync>> = Arc::new(Box::new(Quad::new(
+        Vec3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        light,
+    )));
+
+    Scene {
+        camera: cornell_camera().lights(lights),
+        world: Bvh::from_objects(objects),
+        width: 600,
+    }
+}
+
+pub fn cornell_smoke() -> Scene {
+    let mut objects: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Vec::new();
+
+    let red: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Lambertian::new(Arc::new(
+        Box::new(SolidColor::new(Vec3::new(0.65, 0.05, 0.05))),
+    ))));
+    let white: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Lambertian::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(0.73, 0.73, 0.73)))),
+    )));
+    let green: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Lambertian::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(0.12, 0.45, 0.15)))),
+    )));
+    let light: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(DiffuseLight::new(
+        Arc::new(Box::new(SolidColor::new(Vec3::new(7.0, 7.0, 7.0)))),
+    )));
+
+    objects.push(Box::new(Quad::new(
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        green,
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        red,
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(113.0, 554.0, 127.0),
+        Vec3::new(330.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 305.0),
+        light.clone(),
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(555.0, 555.0, 555.0),
+        Vec3::new(-555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    )));
+    objects.push(Box::new(Quad::new(
+        Vec3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        white.clone(),
+    )));
+
+    let box1: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Arc::new(Box::new(
+        Cuboid::new(Vec3::ZERO, Vec3::new(165.0, 330.0, 165.0), white.clone()),
+    ));
+    let box1: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> =
+        Arc::new(Box::new(RotateY::new(box1, 15.0)));
+    let box1: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> =
+        Arc::new(Box::new(Translate::new(box1, Vec3::new(265.0, 0.0, 295.0))));
+    objects.push(Box::new(ConstantMedium::new(
+        box1,
+        0.01,
+        Arc::new(Box::new(SolidColor::new(Vec3::ZERO))),
+    )));
+
+    let box2: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> = Arc::new(Box::new(
+        Cuboid::new(Vec3::ZERO, Vec3::new(165.0, 165.0, 165.0), white.clone()),
+    ));
+    let box2: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> =
+        Arc::new(Box::new(RotateY::new(box2, -18.0)));
+    let box2: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> =
+        Arc::new(Box::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0))));
+    objects.push(Box::new(ConstantMedium::new(
+        box2,
+        0.01,
+        Arc::new(Box::new(SolidColor::new(Vec3::ONE))),
+    )));
+
+    let lights: Arc<Box<dyn Sampleable + Send + Sync>> = Arc::new(Box::new(Quad::new(
+        Vec3::new(113.0, 554.0, 127.0),
+        Vec3::new(330.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 305.0),
+        light,
+    )));
+
+    Scene {
+        camera: cornell_camera().lights(lights),
+        world: Bvh::from_objects(objects),
+        width: 600,
+    }
+}
@@ -0,0 +1,961 @@
+//! Declarative scene files in [RON](https://github.com/ron-rs/ron)
+//!
+//! A scene names its textures and materials once and refers to them by name:
+//!
+//! ```ron
+//! Scene(
+//!     width: 600,
+//!     camera: (
+//!         aspect_ratio: 1.0,
+//!         fov: 40.0,
+//!         pos: (278.0, 278.0, -800.0),
+//!         look_at: (278.0, 278.0, 0.0),
+//!         background: Color((0.0, 0.0, 0.0)),
+//!     ),
+//!     textures: {
+//!         "white": Solid((0.73, 0.73, 0.73)),
+//!         "light": Solid((15.0, 15.0, 15.0)),
+//!     },
+//!     materials: {
+//!         "white": Lambertian(texture: "white"),
+//!         "light": DiffuseLight(texture: "light"),
+//!     },
+//!     objects: [
+//!         Cuboid(a: (0.0, 0.0, 0.0), b: (165.0, 330.0, 165.0), material: "white"),
+//!         Transform(
+//!             object: Sphere(center: (0.0, 0.0, 0.0), radius: 1.0, material: "white"),
+//!             ops: [Scale((2.0, 1.0, 1.0)), RotateY(15.0), Translate((265.0, 0.0, 295.0))],
+//!         ),
+//!     ],
+//!     lights: [
+//!         Quad(q: (343.0, 554.0, 332.0), u: (-130.0, 0.0, 0.0), v: (0.0, 0.0, -105.0), material: "light"),
+//!     ],
+//! )
+//! ```
+//!
+//! Relative paths (images, obj files) are resolved against the scene file's directory.
+
+pub mod builtin;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    adaptive::AdaptiveSampling,
+    background::{BackgroundRef, Color, EnvironmentMap, Gradient},
+    camera::Camera,
+    material::{
+        Dielectric, DiffuseLight, Isotropic, Lambertian, Metal, NormalMap, NormalMapped,
+        Principled, RefractiveIndex,
+    },
+    math::{Real, Vec3},
+    obj::{load_obj, ObjError},
+    primitive::{ConstantMedium, Cuboid, Quad, Sphere},
+    texture::{
+        CheckerTexture, ColorSpace, Filter, ImageTexture, NoisePattern, NoiseTexture,
+        SolidCheckerTexture, SolidColor, Texture, Wrap,
+    },
+    tonemap::ToneMapper,
+    world::{
+        bvh::{AabbHittable, Bvh, BvhStats, HasAabb},
+        instance::Transform,
+        list::Lights,
+    },
+    MaterialRef, Sampleable,
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse(ron::error::SpannedError),
+    UnknownTexture {
+        name: String,
+        referenced_by: String,
+    },
+    UnknownMaterial {
+        name: String,
+        referenced_by: String,
+    },
+    /// A texture that (indirectly) refers to itself
+    TextureCycle(String),
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    Obj {
+        path: PathBuf,
+        source: ObjError,
+    },
+    /// The scene has no objects to render
+    Empty,
+    /// A light of a kind that can not be sampled, only spheres and quads can
+    UnsampleableLight(&'static str),
+    /// A tone mapper whose parameters make no sense, like a non-positive white point
+    InvalidToneMapper(ToneMapper),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => write!(f, "failed to read {path:?}: {source}"),
+            SceneError::Parse(err) => write!(f, "failed to parse scene: {err}"),
+            SceneError::UnknownTexture {
+                name,
+                referenced_by,
+            } => write!(f, "{referenced_by} refers to unknown texture {name:?}"),
+            SceneError::UnknownMaterial {
+                name,
+                referenced_by,
+            } => write!(f, "{referenced_by} refers to unknown material {name:?}"),
+            SceneError::TextureCycle(name) => write!(f, "texture {name:?} refers to itself"),
+            SceneError::Image { path, source } => {
+                write!(f, "failed to load image {path:?}: {source}")
+            }
+            SceneError::Obj { path, source } => write!(f, "failed to load {path:?}: {source}"),
+            SceneError::Empty => write!(f, "scene contains no objects"),
+            SceneError::UnsampleableLight(kind) => write!(
+                f,
+                "{kind} can not be sampled as a light, only spheres and quads can"
+            ),
+            SceneError::InvalidToneMapper(tone_mapper) => {
+                write!(
+                    f,
+                    "invalid tone mapper {tone_mapper:?}, white must be positive"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Parse(err) => Some(err),
+            SceneError::Image { source, .. } => Some(source),
+            SceneError::Obj { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// A loaded scene, ready for [`Camera::render_to_path`]
+pub struct Scene {
+    pub camera: Camera,
+    pub world: Bvh,
+    pub width: u32,
+}
+
+impl Scene {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&source, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Parses a scene, relative paths in it are resolved against `base_dir`
+    pub fn parse(source: &str, base_dir: impl AsRef<Path>) -> Result<Self, SceneError> {
+        // optional fields are written without `Some(..)`
+        let desc: SceneDesc = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)
+            .map_err(SceneError::Parse)?;
+        desc.build(base_dir.as_ref())
+    }
+
+    pub fn stats(&self) -> SceneStats<'_> {
+        SceneStats { scene: self }
+    }
+}
+
+/// Resolution, camera settings and BVH shape of a [`Scene`], for printing
+pub struct SceneStats<'a> {
+    scene: &'a Scene,
+}
+
+impl fmt::Display for SceneStats<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Scene {
+            camera,
+            world,
+            width,
+        } = self.scene;
+        let BvhStats {
+            leaves,
+            nodes,
+            depth,
+        } = world.stats();
+        let aabb = world.aabb();
+
+        writeln!(f, "resolution: {width}x{}", camera.output_height(*width))?;
+        writeln!(f, "{camera}")?;
+        writeln!(
+            f,
+            "objects: {leaves}, bvh nodes: {nodes}, bvh depth: {depth}"
+        )?;
+        write!(f, "bounds: {} - {}", aabb.min(), aabb.max())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "Scene", deny_unknown_fields)]
+struct SceneDesc {
+    #[serde(default = "default_width")]
+    width: u32,
+    #[serde(default)]
+    camera: CameraDesc,
+    #[serde(default)]
+    textures: HashMap<String, TextureDesc>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDesc>,
+    objects: Vec<ObjectDesc>,
+    /// Objects sampled explicitly as light sources, usually the emissive ones in `objects`.
+    /// Only spheres and quads can be sampled.
+    #[serde(default)]
+    lights: Vec<ObjectDesc>,
+}
+
+fn default_width() -> u32 {
+    1280
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CameraDesc {
+    aspect_ratio: Option<Real>,
+    fov: Option<Real>,
+    pos: Option<Vec3>,
+    look_at: Option<Vec3>,
+    up: Option<Vec3>,
+    samples_per_pixel: Option<u32>,
+    max_depth: Option<u32>,
+    defocus_angle: Option<Real>,
+    focus_distance: Option<Real>,
+    background: Option<BackgroundDesc>,
+    shutter: Option<(Real, Real)>,
+    tone_mapper: Option<ToneMapper>,
+    exposure: Option<Real>,
+    seed: Option<u64>,
+    adaptive: Option<AdaptiveSampling>,
+}
+
+#[derive(Debug, Deserialize)]
+enum BackgroundDesc {
+    /// The white-to-blue gradient of the books
+    Sky,
+    Color(Vec3),
+    Gradient {
+        bottom: Vec3,
+        top: Vec3,
+    },
+    /// An equirectangular image, usually HDR, that is also sampled as a light
+    Environment {
+        path: PathBuf,
+        /// Degrees around the y axis
+        #[serde(default)]
+        rotation: Real,
+        #[serde(default = "default_intensity")]
+        intensity: Real,
+        /// By default `Linear` for float images (HDR, EXR), `Srgb` otherwise
+        #[serde(default)]
+        color_space: Option<ColorSpace>,
+    },
+}
+
+fn default_intensity() -> Real {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+enum TextureDesc {
+    Solid(Vec3),
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        filter: Filter,
+        #[serde(default)]
+        wrap: Wrap,
+        /// By default `Linear` for float images (HDR, EXR) and for the maps of `NormalMapped`,
+        /// `Srgb` otherwise
+        #[serde(default)]
+        color_space: Option<ColorSpace>,
+    },
+    /// 3D checker pattern in space
+    SolidChecker {
+        scale: Real,
+        even: String,
+        odd: String,
+    },
+    /// 2D checker pattern in uv
+    Checker {
+        lng_scale: u32,
+        lat_scale: u32,
+        even: String,
+        odd: String,
+    },
+    Noise {
+        #[serde(default)]
+        seed: u64,
+        scale: Real,
+        #[serde(default = "default_noise_pattern")]
+        pattern: NoisePattern,
+    },
+}
+
+fn default_noise_pattern() -> NoisePattern {
+    NoisePattern::Plain
+}
+
+fn default_roughness() -> Real {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+enum MaterialDesc {
+    Lambertian {
+        texture: String,
+    },
+    Metal {
+        texture: String,
+        #[serde(default)]
+        fuzz: Real,
+    },
+    Dielectric {
+        refraction_index: Real,
+        /// Beer–Lambert coefficients, per unit of distance traveled inside
+        #[serde(default)]
+        absorption: Vec3,
+        /// Overrides `refraction_index` with one per wavelength
+        #[serde(default)]
+        dispersion: Option<RefractiveIndex>,
+    },
+    /// glTF's metallic-roughness, the factors multiply the channels of `metallic_roughness`
+    Principled {
+        base_color: String,
+        #[serde(default)]
+        metallic: Real,
+        #[serde(default = "default_roughness")]
+        roughness: Real,
+        #[serde(default)]
+        metallic_roughness: Option<String>,
+    },
+    /// Another material shaded with a perturbed normal
+    NormalMapped {
+        material: Box<MaterialDesc>,
+        map: NormalMapDesc,
+    },
+    DiffuseLight {
+        texture: String,
+    },
+    Isotropic {
+        texture: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+enum NormalMapDesc {
+    Normals(String),
+    Heights { texture: String, strength: Real },
+}
+
+#[derive(Debug, Deserialize)]
+enum ObjectDesc {
+    Sphere {
+        center: Vec3,
+        radius: Real,
+        material: String,
+    },
+    MovingSphere {
+        center0: Vec3,
+        center1: Vec3,
+        radius: Real,
+        material: String,
+    },
+    Quad {
+        q: Vec3,
+        u: Vec3,
+        v: Vec3,
+        material: String,
+    },
+    Cuboid {
+        a: Vec3,
+        b: Vec3,
+        material: String,
+    },
+    Obj {
+        path: PathBuf,
+        /// Used for models without an MTL material
+        material: String,
+    },
+    Medium {
+        boundary: Box<ObjectDesc>,
+        density: Real,
+        texture: String,
+    },
+    Transform {
+        object: Box<ObjectDesc>,
+        /// Applied in order
+        ops: Vec<TransformOp>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+enum TransformOp {
+    Translate(Vec3),
+    /// Degrees around the y axis
+    RotateY(Real),
+    /// Degrees around an arbitrary axis
+    Rotate {
+        axis: Vec3,
+        angle: Real,
+    },
+    Scale(Vec3),
+}
+
+type TextureRef = Arc<Box<dyn Texture + Send + Sync>>;
+
+struct Builder<'a> {
+    base_dir: &'a Path,
+    texture_descs: &'a HashMap<String, TextureDesc>,
+    /// By name and the color space of images that don't say, see [`Builder::data_texture`]
+    textures: HashMap<(String, Option<ColorSpace>), TextureRef>,
+    materials: HashMap<String, MaterialRef>,
+}
+
+impl SceneDesc {
+    fn build(self, base_dir: &Path) -> Result<Scene, SceneError> {
+        let mut builder = Builder {
+            base_dir,
+            texture_descs: &self.textures,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+        };
+        for (name, desc) in self.materials.iter() {
+            let material = builder.material(name, desc)?;
+            builder.materials.insert(name.clone(), material);
+        }
+
+        let objects = self
+            .objects
+            .iter()
+            .map(|desc| builder.object(desc))
+            .collect::<Result<Vec<_>, _>>()?;
+        if objects.is_empty() {
+            return Err(SceneError::Empty);
+        }
+        let world = Bvh::from_objects(objects);
+
+        let mut camera = self.camera.build()?;
+        if let Some(background) = &self.camera.background {
+            camera = camera.background(builder.background(background)?);
+        }
+        let lights = self
+            .lights
+            .iter()
+            .map(|desc| builder.light(desc))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(lights) = Lights::new(lights) {
+            camera = camera.lights(Arc::new(Box::new(lights)));
+        }
+
+        Ok(Scene {
+            camera,
+            world,
+            width: self.width,
+        })
+    }
+}
+
+impl CameraDesc {
+    fn build(&self) -> Result<Camera, SceneError> {
+        let mut camera = Camera::new(self.aspect_ratio.unwrap_or(16.0 / 9.0));
+        if let Some(fov) = self.fov {
+            camera = camera.fov(fov);
+        }
+        if let Some(pos) = self.pos {
+            camera = camera.pos(pos);
+        }
+        if let Some(look_at) = self.look_at {
+            camera = camera.look_at(look_at);
+        }
+        if let Some(up) = self.up {
+            camera = camera.up(up);
+        }
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            camera = camera.samples_per_pixel(samples_per_pixel);
+        }
+        if let Some(max_depth) = self.max_depth {
+            camera = camera.max_depth(max_depth);
+        }
+        if let Some(defocus_angle) = self.defocus_angle {
+            camera = camera.defocus_angle(defocus_angle);
+        }
+        if let Some(focus_distance) = self.focus_distance {
+            camera = camera.focus_distance(focus_distance);
+        }
+        if let Some((open, close)) = self.shutter {
+            camera = camera.shutter(open, close);
+        }
+        if let Some(tone_mapper) = self.tone_mapper {
+            if !tone_mapper.is_valid() {
+                return Err(SceneError::InvalidToneMapper(tone_mapper));
+            }
+            camera = camera.tone_mapper(tone_mapper);
+        }
+        if let Some(exposure) = self.exposure {
+            camera = camera.exposure(exposure);
+        }
+        if let Some(seed) = self.seed {
+            camera = camera.seed(seed);
+        }
+        if let Some(adaptive) = self.adaptive {
+            camera = camera.adaptive(adaptive);
+        }
+        Ok(camera)
+    }
+}
+
+impl Builder<'_> {
+    fn texture(&mut self, name: &str, referenced_by: &str) -> Result<TextureRef, SceneError> {
+        self.texture_inner(name, referenced_by, None, &mut Vec::new())
+    }
+
+    /// A texture holding data rather than colors, e.g. normals, whose images are linear
+    /// unless they say otherwise
+    fn data_texture(&mut self, name: &str, referenced_by: &str) -> Result<TextureRef, SceneError> {
+        self.texture_inner(
+            name,
+            referenced_by,
+            Some(ColorSpace::Linear),
+            &mut Vec::new(),
+        )
+    }
+
+    /// `color_space` is used for images that don't specify one, `visiting` holds the textures
+    /// being built, to report cycles instead of overflowing
+    fn texture_inner(
+        &mut self,
+        name: &str,
+        referenced_by: &str,
+        color_space: Option<ColorSpace>,
+        visiting: &mut Vec<String>,
+    ) -> Result<TextureRef, SceneError> {
+        let key = (name.to_string(), color_space);
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+        let desc = self
+            .texture_descs
+            .get(name)
+            .ok_or_else(|| SceneError::UnknownTexture {
+                name: name.to_string(),
+                referenced_by: referenced_by.to_string(),
+            })?;
+        if visiting.iter().any(|n| n == name) {
+            return Err(SceneError::TextureCycle(name.to_string()));
+        }
+        visiting.push(name.to_string());
+
+        let this = format!("texture {name:?}");
+        let texture: TextureRef = match desc {
+            TextureDesc::Solid(color) => Arc::new(Box::new(SolidColor::new(*color))),
+            TextureDesc::Image {
+                path,
+                filter,
+                wrap,
+                color_space: image_color_space,
+            } => {
+                let path = self.base_dir.join(path);
+                let texture = ImageTexture::from_path(&path, image_color_space.or(color_space))
+                    .map_err(|source| SceneError::Image { path, source })?;
+                Arc::new(Box::new(texture.filter(*filter).wrap(*wrap)))
+            }
+            TextureDesc::SolidChecker { scale, even, odd } => {
+                let even = self.texture_inner(even, &this, color_space, visiting)?;
+                let odd = self.texture_inner(odd, &this, color_space, visiting)?;
+                Arc::new(Box::new(SolidCheckerTexture::new(*scale, even, odd)))
+            }
+            TextureDesc::Checker {
+                lng_scale,
+                lat_scale,
+                even,
+                odd,
+            } => {
+                let even = self.texture_inner(even, &this, color_space, visiting)?;
+                let odd = self.texture_inner(odd, &this, color_space, visiting)?;
+                Arc::new(Box::new(CheckerTexture::new(
+                    *lng_scale, *lat_scale, even, odd,
+                )))
+            }
+            TextureDesc::Noise {
+                seed,
+                scale,
+                pattern,
+            } => Arc::new(Box::new(NoiseTexture::new(*seed, *scale).pattern(*pattern))),
+        };
+
+        visiting.pop();
+        self.textures.insert(key, texture.clone());
+        Ok(texture)
+    }
+
+    fn material(&mut self, name: &str, desc: &MaterialDesc) -> Result<MaterialRef, SceneError> {
+        let this = format!("material {name:?}");
+        let material: MaterialRef = match desc {
+            MaterialDesc::Lambertian { texture } => {
+                Arc::new(Box::new(Lambertian::new(self.texture(texture, &this)?)))
+            }
+            MaterialDesc::Metal { texture, fuzz } => Arc::new(Box::new(
+                Metal::new(self.texture(texture, &this)?).fuzz(*fuzz),
+            )),
+            MaterialDesc::Dielectric {
+                refraction_index,
+                absorption,
+                dispersion,
+            } => {
+                let dielectric = match dispersion {
+                    Some(dispersion) => Dielectric::dispersive(*dispersion),
+                    None => Dielectric::new(*refraction_index),
+                };
+                Arc::new(Box::new(dielectric.absorption(*absorption)))
+            }
+            MaterialDesc::Principled {
+                base_color,
+                metallic,
+                roughness,
+                metallic_roughness,
+            } => {
+                let mut material = Principled::new(self.texture(base_color, &this)?)
+                    .metallic(*metallic)
+                    .roughness(*roughness);
+                if let Some(texture) = metallic_roughness {
+                    material = material.metallic_roughness(self.data_texture(texture, &this)?);
+                }
+                Arc::new(Box::new(material))
+            }
+            MaterialDesc::NormalMapped { material, map } => {
+                let map = match map {
+                    NormalMapDesc::Normals(texture) => {
+                        NormalMap::Normals(self.data_texture(texture, &this)?)
+                    }
+                    NormalMapDesc::Heights { texture, strength } => NormalMap::Heights {
+                        texture: self.data_texture(texture, &this)?,
+                        strength: *strength,
+                    },
+                };
+                let material = self.material(name, material)?;
+                Arc::new(Box::new(NormalMapped::new(material, map)))
+            }
+            MaterialDesc::DiffuseLight { texture } => {
+                Arc::new(Box::new(DiffuseLight::new(self.texture(texture, &this)?)))
+            }
+            MaterialDesc::Isotropic { texture } => {
+                Arc::new(Box::new(Isotropic::new(self.texture(texture, &this)?)))
+            }
+        };
+        Ok(material)
+    }
+
+    fn find_material(&self, name: &str, referenced_by: &str) -> Result<MaterialRef, SceneError> {
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SceneError::UnknownMaterial {
+                name: name.to_string(),
+                referenced_by: referenced_by.to_string(),
+            })
+    }
+
+    fn background(&self, desc: &BackgroundDesc) -> Result<BackgroundRef, SceneError> {
+        let background: BackgroundRef = match desc {
+            BackgroundDesc::Sky => Arc::new(Box::new(Gradient::default())),
+            BackgroundDesc::Color(color) => Arc::new(Box::new(Color(*color))),
+            BackgroundDesc::Gradient { bottom, top } => Arc::new(Box::new(Gradient {
+                bottom: *bottom,
+                top: *top,
+            })),
+            BackgroundDesc::Environment {
+                path,
+                rotation,
+                intensity,
+                color_space,
+            } => {
+                let path = self.base_dir.join(path);
+                let map = EnvironmentMap::from_path(&path, *color_space)
+                    .map_err(|source| SceneError::Image { path, source })?;
+                Arc::new(Box::new(map.rotation(*rotation).intensity(*intensity)))
+            }
+        };
+        Ok(background)
+    }
+
+    /// Like [`Builder::object`], for the kinds of objects that can be sampled as lights
+    fn light(
+        &mut self,
+        desc: &ObjectDesc,
+    ) -> Result<Box<dyn Sampleable + Send + Sync>, SceneError> {
+        let light: Box<dyn Sampleable + Send + Sync> = match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                let material = self.find_material(material, "a light")?;
+                Box::new(Sphere::new(*center, *radius, material))
+            }
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                radius,
+                material,
+            } => {
+                let material = self.find_material(material, "a light")?;
+                Box::new(Sphere::moving(*center0, *center1, *radius, material))
+            }
+            ObjectDesc::Quad { q, u, v, material } => {
+                let material = self.find_material(material, "a light")?;
+                Box::new(Quad::new(*q, *u, *v, material))
+            }
+            ObjectDesc::Cuboid { .. } => return Err(SceneError::UnsampleableLight("Cuboid")),
+            ObjectDesc::Obj { .. } => return Err(SceneError::UnsampleableLight("Obj")),
+            ObjectDesc::Medium { .. } => return Err(SceneError::UnsampleableLight("Medium")),
+            ObjectDesc::Transform { .. } => return Err(SceneError::UnsampleableLight("Transform")),
+        };
+        Ok(light)
+    }
+
+    fn object(
+        &mut self,
+        desc: &ObjectDesc,
+    ) -> Result<Box<dyn AabbHittable<MaterialRef> + Send + Sync>, SceneError> {
+        let object: Box<dyn AabbHittable<MaterialRef> + Send + Sync> = match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                let material = self.find_material(material, "a sphere")?;
+                Box::new(Sphere::new(*center, *radius, material))
+            }
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                radius,
+                material,
+            } => {
+                let material = self.find_material(material, "a moving sphere")?;
+                Box::new(Sphere::moving(*center0, *center1, *radius, material))
+            }
+            ObjectDesc::Quad { q, u, v, material } => {
+                let material = self.find_material(material, "a quad")?;
+                Box::new(Quad::new(*q, *u, *v, material))
+            }
+            ObjectDesc::Cuboid { a, b, material } => {
+                let material = self.find_material(material, "a cuboid")?;
+                Box::new(Cuboid::new(*a, *b, material))
+            }
+            ObjectDesc::Obj { path, material } => {
+                let material = self.find_material(material, &format!("obj {path:?}"))?;
+                let path = self.base_dir.join(path);
+                let mesh =
+                    load_obj(&path, material).map_err(|source| SceneError::Obj { path, source })?;
+                Box::new(mesh)
+            }
+            ObjectDesc::Medium {
+                boundary,
+                density,
+                texture,
+            } => {
+                let boundary = Arc::new(self.object(boundary)?);
+                let texture = self.texture(texture, "a medium")?;
+                Box::new(ConstantMedium::new(boundary, *density, texture))
+            }
+            ObjectDesc::Transform { object, ops } => {
+                let object = Arc::new(self.object(object)?);
+                let transform = ops
+                    .iter()
+                    .fold(Transform::new(object), |transform, op| match op {
+                        TransformOp::Translate(offset) => transform.translate(*offset),
+                        TransformOp::RotateY(angle) => transform.rotate_y(*angle),
+                        TransformOp::Rotate { axis, angle } => transform.rotate(*axis, *angle),
+                        TransformOp::Scale(scale) => transform.scale(*scale),
+                    });
+                Box::new(transform)
+            }
+        };
+        Ok(object)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Hittable, Ray};
+
+    use super::*;
+
+    const SCENE: &str = r#"
+        Scene(
+            width: 64,
+            camera: (
+                aspect_ratio: 1.0,
+                pos: (0.0, 0.0, 5.0),
+                look_at: (0.0, 0.0, 0.0),
+                background: Gradient(bottom: (1.0, 1.0, 1.0), top: (0.2, 0.4, 1.0)),
+            ),
+            textures: {
+                "white": Solid((1.0, 1.0, 1.0)),
+                "black": Solid((0.0, 0.0, 0.0)),
+                "checker": SolidChecker(scale: 0.5, even: "white", odd: "black"),
+            },
+            materials: {
+                "checker": Lambertian(texture: "checker"),
+                "glass": Dielectric(refraction_index: 1.5),
+                "prism": Dielectric(
+                    refraction_index: 1.5,
+                    absorption: (0.1, 0.0, 0.1),
+                    dispersion: Some(Cauchy(a: 1.5, b: 0.004)),
+                ),
+                "brass": Principled(base_color: "white", metallic: 1.0, roughness: 0.3),
+                "bumpy": NormalMapped(
+                    material: Lambertian(texture: "white"),
+                    map: Heights(texture: "checker", strength: 0.01),
+                ),
+            },
+            objects: [
+                Transform(
+                    object: Cuboid(a: (-1.0, -1.0, -1.0), b: (1.0, 1.0, 1.0), material: "checker"),
+                    ops: [Scale((2.0, 1.0, 1.0)), Translate((0.0, 3.0, 0.0))],
+                ),
+                Sphere(center: (0.0, 0.0, 0.0), radius: 1.0, material: "glass"),
+            ],
+        )
+    "#;
+
+    #[test]
+    fn test_load_scene() {
+        let scene = Scene::parse(SCENE, "").unwrap();
+        assert_eq!(scene.width, 64);
+
+        let ray = Ray::new(Vec3::new(1.5, 3.0, 5.0), Vec3::NEG_Z);
+        let record = scene.world.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((record.t - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_load_scene_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/cornell_box.ron");
+        let scene = Scene::from_path(path).unwrap();
+        assert_eq!(scene.width, 600);
+
+        // the ceiling light
+        let ray = Ray::new(Vec3::new(278.0, 278.0, 278.0), Vec3::Y);
+        let record = scene.world.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        let emitted = record
+            .material
+            .unwrap()
+            .emitted(record.u, record.v, record.point);
+        assert_eq!(emitted, Vec3::splat(15.0));
+    }
+
+    #[test]
+    fn test_unknown_references() {
+        let source = SCENE.replace(r#"material: "glass""#, r#"material: "gold""#);
+        let err = Scene::parse(&source, "").err().unwrap();
+        assert!(matches!(err, SceneError::UnknownMaterial { ref name, .. } if name == "gold"));
+
+        let source = SCENE.replace(r#"odd: "black""#, r#"odd: "checker""#);
+        let err = Scene::parse(&source, "").err().unwrap();
+        assert!(matches!(err, SceneError::TextureCycle(_)));
+
+        let source = SCENE.replace(r#"even: "white""#, r#"even: "red""#);
+        let err = Scene::parse(&source, "").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            r#"texture "checker" refers to unknown texture "red""#
+        );
+
+        let gradient = "Gradient(bottom: (1.0, 1.0, 1.0), top: (0.2, 0.4, 1.0))";
+        let source = SCENE.replace(gradient, r#"Environment(path: "sky.hdr")"#);
+        let err = Scene::parse(&source, "").err().unwrap();
+        assert!(matches!(err, SceneError::Image { ref path, .. } if path.ends_with("sky.hdr")));
+    }
+
+    #[test]
+    fn test_image_color_space() {
+        let image = |color_space: &str| TextureDesc::Image {
+            path: PathBuf::from("assets/earthmap.jpg"),
+            filter: Filter::Nearest,
+            wrap: Wrap::Clamp,
+            color_space: ron::from_str(color_space).unwrap(),
+        };
+        let texture_descs = HashMap::from([
+            ("earth".to_string(), image("None")),
+            ("srgb".to_string(), image("Some(Srgb)")),
+        ]);
+        let mut builder = Builder {
+            base_dir: Path::new(env!("CARGO_MANIFEST_DIR")),
+            texture_descs: &texture_descs,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+        };
+        let value = |texture: TextureRef| texture.value(0.3, 0.6, Vec3::ZERO);
+
+        // the same jpg is decoded from sRGB as a color, and read as is as a normal map
+        let color = value(builder.texture("earth", "a test").unwrap());
+        let data = value(builder.data_texture("earth", "a test").unwrap());
+        assert!(color.cmplt(data).all(), "{color} {data}");
+        // unless the texture says otherwise
+        let srgb = value(builder.data_texture("srgb", "a test").unwrap());
+        assert_eq!(srgb, color);
+    }
+
+    #[test]
+    fn test_unsampleable_lights() {
+        let with_light = |light: &str| {
+            let scene = SCENE.trim_end().strip_suffix(')').unwrap();
+            format!("{scene} lights: [{light}])")
+        };
+        let quad = r#"Quad(q: (0.0, 5.0, 0.0), u: (1.0, 0.0, 0.0), v: (0.0, 0.0, 1.0), material: "glass")"#;
+        assert!(Scene::parse(&with_light(quad), "").is_ok());
+
+        // a moved quad is not sampled where it is
+        let moved = format!("Transform(object: {quad}, ops: [Translate((0.0, 1.0, 0.0))])");
+        let err = Scene::parse(&with_light(&moved), "").err().unwrap();
+        assert!(matches!(err, SceneError::UnsampleableLight("Transform")));
+
+        let cuboid = r#"Cuboid(a: (0.0, 0.0, 0.0), b: (1.0, 1.0, 1.0), material: "glass")"#;
+        let err = Scene::parse(&with_light(cuboid), "").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Cuboid can not be sampled as a light, only spheres and quads can"
+        );
+    }
+    #[test]
+    fn test_invalid_tone_mapper() {
+        let with_white = |white: &str| {
+            SCENE.replace(
+                "aspect_ratio: 1.0,",
+                &format!("aspect_ratio: 1.0, tone_mapper: ReinhardExtended(white: {white}),"),
+            )
+        };
+        assert!(Scene::parse(&with_white("2.0"), "").is_ok());
+
+        let err = Scene::parse(&with_white("0.0"), "").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid tone mapper ReinhardExtended { white: 0.0 }, white must be positive"
+        );
+    }
+}
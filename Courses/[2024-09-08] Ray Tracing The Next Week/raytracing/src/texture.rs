@@ -1,10 +1,28 @@
 use std::{path::Path, sync::Arc};
 
-use glam::Vec3;
-use image::{ImageBuffer, Rgb};
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    ColorType, DynamicImage, ImageError, ImageResult, Rgb32FImage,
+};
+use serde::Deserialize;
+
+use crate::math::{from_f32_array, Real, Vec3};
+use crate::perlin::Perlin;
+use crate::HitRecord;
 
 pub trait Texture {
-    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3;
+    fn value(&self, u: Real, v: Real, point: Vec3) -> Vec3;
+
+    /// The value averaged over a footprint `footprint` uv units wide, only textures that
+    /// filter tell it apart from [`Texture::value`]
+    fn filtered_value(&self, u: Real, v: Real, point: Vec3, _footprint: Real) -> Vec3 {
+        self.value(u, v, point)
+    }
+
+    /// [`Texture::filtered_value`] where `record` hit
+    fn value_at(&self, record: &HitRecord) -> Vec3 {
+        self.filtered_value(record.u, record.v, record.point, record.footprint)
+    }
 }
 
 pub struct SolidColor {
@@ -18,20 +36,20 @@ impl SolidColor {
 }
 
 impl Texture for SolidColor {
-    fn value(&self, _u: f32, _v: f32, _point: Vec3) -> Vec3 {
+    fn value(&self, _u: Real, _v: Real, _point: Vec3) -> Vec3 {
         self.albedo
     }
 }
 
 pub struct SolidCheckerTexture {
-    inv_scale: f32,
+    inv_scale: Real,
     even: Arc<Box<dyn Texture + Send + Sync>>,
     odd: Arc<Box<dyn Texture + Send + Sync>>,
 }
 
 impl SolidCheckerTexture {
     pub fn new(
-        scale: f32,
+        scale: Real,
         even: Arc<Box<dyn Texture + Send + Sync>>,
         odd: Arc<Box<dyn Texture + Send + Sync>>,
     ) -> Self {
@@ -45,7 +63,7 @@ impl SolidCheckerTexture {
 }
 
 impl Texture for SolidCheckerTexture {
-    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3 {
+    fn value(&self, u: Real, v: Real, point: Vec3) -> Vec3 {
         let p = point
             .to_array()
             .map(|v| (self.inv_scale * v).floor() as i32)
@@ -84,8 +102,8 @@ impl CheckerTexture {
 }
 
 impl Texture for CheckerTexture {
-    fn value(&self, u: f32, v: f32, point: Vec3) -> Vec3 {
-        let p = [u * self.lng_scale as f32, v * self.lat_scale as f32]
+    fn value(&self, u: Real, v: Real, point: Vec3) -> Vec3 {
+        let p = [u * self.lng_scale as Real, v * self.lat_scale as Real]
             .map(|v| v.floor() as i32)
             .iter()
             .sum::<i32>();
@@ -98,46 +116,379 @@ impl Texture for CheckerTexture {
     }
 }
 
-pub struct ImageTexture {
-    // ! Use ImageBuffer directly causes rayon error, so use Vec<u8> instead
-    data: Vec<u8>,
-    width: u32,
-    height: u32,
+/// How [`ImageTexture`] looks up texels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Filter {
+    /// The texel under `(u, v)`
+    #[default]
+    Nearest,
+    /// The four texels around `(u, v)`, weighted by distance
+    Bilinear,
+    /// Bilinear in the two mip levels closest to the ray's footprint, blended
+    Trilinear,
 }
 
-impl ImageTexture {
-    pub fn new(image: ImageBuffer<Rgb<u8>, Vec<u8>>) -> Self {
-        let width = image.width();
-        let height = image.height();
-        let data = image.to_vec();
+/// What [`ImageTexture`] shows outside of `[0, 1]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Wrap {
+    /// The texels on the border stretch out
+    #[default]
+    Clamp,
+    /// The image tiles
+    Repeat,
+    /// The image tiles, every other tile flipped
+    Mirror,
+}
+
+impl Wrap {
+    fn index(self, i: i64, len: usize) -> usize {
+        let len = len as i64;
+        let i = match self {
+            Wrap::Clamp => i.clamp(0, len - 1),
+            Wrap::Repeat => i.rem_euclid(len),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * len);
+                if i < len {
+                    i
+                } else {
+                    2 * len - 1 - i
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+/// How the values of an image are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum ColorSpace {
+    /// Most 8 bit color images
+    Srgb,
+    /// Normal maps, height maps and HDR images
+    Linear,
+}
+
+impl ColorSpace {
+    /// Float images (HDR, EXR) are linear, the others sRGB
+    pub fn of(image: &DynamicImage) -> Self {
+        match image.color() {
+            ColorType::Rgb32F | ColorType::Rgba32F => ColorSpace::Linear,
+            _ => ColorSpace::Srgb,
+        }
+    }
+
+    fn decode(self, value: f32) -> f32 {
+        match self {
+            ColorSpace::Srgb if value <= 0.04045 => value / 12.92,
+            ColorSpace::Srgb => ((value + 0.055) / 1.055).powf(2.4),
+            ColorSpace::Linear => value,
+        }
+    }
+}
+
+/// One level of the mip pyramid, in linear values
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec3>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: i64, y: i64, wrap: Wrap) -> Vec3 {
+        let x = wrap.index(x, self.width);
+        let y = wrap.index(y, self.height);
+        self.texels[y * self.width + x]
+    }
+
+    /// Every 2x2 block averaged into one texel, an odd row or column is folded into the last
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = vec![Vec3::ZERO; width * height];
+        let mut counts = vec![0u32; width * height];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = (y / 2).min(height - 1) * width + (x / 2).min(width - 1);
+                texels[index] += self.texels[y * self.width + x];
+                counts[index] += 1;
+            }
+        }
+        for (texel, count) in texels.iter_mut().zip(counts) {
+            *texel /= count as Real;
+        }
         Self {
-            data,
             width,
             height,
+            texels,
         }
     }
 
-    pub fn from_path(path: impl AsRef<Path>) -> Self {
-        let image = image::open(path).unwrap().to_rgb8();
-        Self::new(image)
+    fn nearest(&self, x: Real, y: Real, wrap: Wrap) -> Vec3 {
+        self.texel(x.floor() as i64, y.floor() as i64, wrap)
+    }
+
+    fn bilinear(&self, x: Real, y: Real, wrap: Wrap) -> Vec3 {
+        // texel centers sit at half coordinates
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let texel = |dx, dy| self.texel(x0 + dx, y0 + dy, wrap);
+        let top = texel(0, 0).lerp(texel(1, 0), fx);
+        let bottom = texel(0, 1).lerp(texel(1, 1), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+pub struct ImageTexture {
+    // ! Use ImageBuffer directly causes rayon error, so use Vec instead
+    /// The full image first, then halved until one texel is left when filtering trilinearly
+    levels: Vec<MipLevel>,
+    filter: Filter,
+    wrap: Wrap,
+}
+
+impl ImageTexture {
+    /// Fails for an image without pixels
+    pub fn new(image: Rgb32FImage, color_space: ColorSpace) -> ImageResult<Self> {
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let texels = image
+            .pixels()
+            .map(|p| from_f32_array(p.0.map(|c| color_space.decode(c))))
+            .collect();
+        let level = MipLevel {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            texels,
+        };
+        Ok(Self {
+            levels: vec![level],
+            filter: Filter::Nearest,
+            wrap: Wrap::Clamp,
+        })
+    }
+
+    /// Without a `color_space`, float images (HDR, EXR) are read as linear and the others as
+    /// sRGB
+    pub fn from_path(path: impl AsRef<Path>, color_space: Option<ColorSpace>) -> ImageResult<Self> {
+        let image = image::open(path)?;
+        let color_space = color_space.unwrap_or_else(|| ColorSpace::of(&image));
+        Self::new(image.to_rgb32f(), color_space)
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self.levels.truncate(1);
+        if filter == Filter::Trilinear {
+            while let Some(level) = self.levels.last().filter(|l| l.width * l.height > 1) {
+                let next = level.downsample();
+                self.levels.push(next);
+            }
+        }
+        self
+    }
+
+    pub fn wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// The linear values of the full image, row by row from the top
+    pub fn texels(&self) -> &[Vec3] {
+        &self.levels[0].texels
+    }
+
+    fn sample(&self, level: usize, u: Real, v: Real) -> Vec3 {
+        let level = &self.levels[level];
+        let x = u * level.width as Real;
+        let y = (1.0 - v) * level.height as Real;
+        match self.filter {
+            Filter::Nearest => level.nearest(x, y, self.wrap),
+            Filter::Bilinear | Filter::Trilinear => level.bilinear(x, y, self.wrap),
+        }
     }
 }
 
 impl Texture for ImageTexture {
-    fn value(&self, u: f32, v: f32, _point: Vec3) -> Vec3 {
-        let u = u.clamp(0.0, 1.0);
-        let v = 1.0 - v.clamp(0.0, 1.0);
+    fn value(&self, u: Real, v: Real, _point: Vec3) -> Vec3 {
+        self.sample(0, u, v)
+    }
+
+    fn filtered_value(&self, u: Real, v: Real, point: Vec3, footprint: Real) -> Vec3 {
+        if self.filter != Filter::Trilinear {
+            return self.value(u, v, point);
+        }
+
+        // the level whose texels are as wide as the footprint
+        let full = &self.levels[0];
+        let texels = footprint * full.width.max(full.height) as Real;
+        let lod = texels.log2().clamp(0.0, (self.levels.len() - 1) as Real);
+        if lod.is_nan() {
+            return self.value(u, v, point);
+        }
+        let fine = lod.floor() as usize;
+        let coarse = (fine + 1).min(self.levels.len() - 1);
+        self.sample(fine, u, v)
+            .lerp(self.sample(coarse, u, v), lod - fine as Real)
+    }
+}
 
-        let i = (u * self.width as f32) as usize;
-        let j = (v * self.height as f32) as usize;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum NoisePattern {
+    /// Smooth noise mapped to `[0, 1]`
+    Plain,
+    /// Sum of several octaves of noise
+    Turbulence,
+    /// Sine stripes along z whose phase is distorted by turbulence
+    Marble,
+}
 
-        let index = (j * self.width as usize + i) * 3;
-        let rgb = self.data.get(index..index + 3).unwrap();
+pub struct NoiseTexture {
+    perlin: Perlin,
+    scale: Real,
+    pattern: NoisePattern,
+}
+
+impl NoiseTexture {
+    pub fn new(seed: u64, scale: Real) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            scale,
+            pattern: NoisePattern::Plain,
+        }
+    }
+
+    pub fn pattern(mut self, pattern: NoisePattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: Real, _v: Real, point: Vec3) -> Vec3 {
+        const TURBULENCE_DEPTH: u32 = 7;
+
+        let p = self.scale * point;
+        let intensity = match self.pattern {
+            NoisePattern::Plain => 0.5 * (1.0 + self.perlin.noise(p)),
+            NoisePattern::Turbulence => self.perlin.turbulence(p, TURBULENCE_DEPTH),
+            NoisePattern::Marble => {
+                0.5 * (1.0 + (p.z + 10.0 * self.perlin.turbulence(point, TURBULENCE_DEPTH)).sin())
+            }
+        };
+        Vec3::splat(intensity)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Rgb;
+
+    use super::*;
+
+    /// A 4x2 gray ramp, 0 / 0.25 / 0.5 / 1.0 in both rows
+    fn ramp() -> ImageTexture {
+        let values = [0.0, 0.25, 0.5, 1.0];
+        let image = Rgb32FImage::from_fn(4, 2, |x, _| Rgb([values[x as usize]; 3]));
+        ImageTexture::new(image, ColorSpace::Linear).unwrap()
+    }
+
+    fn gray(texture: &ImageTexture, u: Real) -> Real {
+        texture.value(u, 0.5, Vec3::ZERO).x
+    }
+
+    #[test]
+    fn test_wrap() {
+        let clamp = ramp();
+        assert_eq!(gray(&clamp, 1.0), 1.0);
+        assert_eq!(gray(&clamp, 1.3), 1.0);
+        assert_eq!(gray(&clamp, -2.0), 0.0);
+
+        let repeat = ramp().wrap(Wrap::Repeat);
+        assert_eq!(gray(&repeat, 1.1), 0.0);
+        assert_eq!(gray(&repeat, -0.1), 1.0);
+
+        let mirror = ramp().wrap(Wrap::Mirror);
+        assert_eq!(gray(&mirror, 1.1), 1.0);
+        assert_eq!(gray(&mirror, 1.9), 0.0);
+        assert_eq!(gray(&mirror, -0.1), 0.0);
+    }
+
+    #[test]
+    fn test_filter() {
+        let nearest = ramp();
+        assert_eq!(gray(&nearest, 0.3), 0.25);
+
+        // halfway between the centers of the second and third texel
+        let bilinear = ramp().filter(Filter::Bilinear);
+        assert!((gray(&bilinear, 0.5) - 0.375).abs() < 1e-6);
+        // the border texels blend with their wrapped neighbours
+        assert_eq!(gray(&bilinear, 0.0), 0.0);
+        let repeat = ramp().filter(Filter::Bilinear).wrap(Wrap::Repeat);
+        assert!((gray(&repeat, 0.0) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mipmaps() {
+        let texture = ramp().filter(Filter::Trilinear);
+        assert_eq!(texture.levels.len(), 3);
+        assert_eq!((texture.levels[1].width, texture.levels[1].height), (2, 1));
+        assert!((texture.levels[2].texels[0].x - 0.4375).abs() < 1e-6);
+
+        let at = |footprint| texture.filtered_value(0.1, 0.5, Vec3::ZERO, footprint).x;
+        // a thin ray sees the full image, a wide one the average of all of it
+        assert_eq!(at(0.0), 0.0);
+        assert!((at(10.0) - 0.4375).abs() < 1e-6);
+        // exactly as wide as a texel of the 2x1 level
+        assert!((at(0.5) - 0.125).abs() < 1e-6);
+        assert!(at(0.4) < at(0.5) && at(0.5) < at(0.6));
+    }
+
+    #[test]
+    fn test_srgb() {
+        let image = Rgb32FImage::from_pixel(1, 1, Rgb([0.0, 0.5, 1.0]));
+        let color = ImageTexture::new(image, ColorSpace::Srgb)
+            .unwrap()
+            .value(0.5, 0.5, Vec3::ZERO);
+        assert!(color.abs_diff_eq(Vec3::new(0.0, 0.214, 1.0), 1e-3));
+    }
+
+    #[test]
+    fn test_color_space_by_format() {
+        let dir = std::env::temp_dir().join(format!(
+            "raytracing-test-color-space-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image = Rgb32FImage::from_pixel(1, 1, Rgb([0.5; 3]));
+        let gray = |name: &str, color_space| {
+            let path = dir.join(name);
+            ImageTexture::from_path(path, color_space)
+                .unwrap()
+                .value(0.5, 0.5, Vec3::ZERO)
+                .x
+        };
+        image::DynamicImage::from(image.clone())
+            .to_rgb8()
+            .save(dir.join("gray.png"))
+            .unwrap();
+        image.save(dir.join("gray.hdr")).unwrap();
+
+        // 8 bit images are sRGB unless told otherwise, float images are linear
+        assert!((gray("gray.png", None) - 0.214).abs() < 1e-2);
+        assert!((gray("gray.png", Some(ColorSpace::Linear)) - 0.5).abs() < 1e-2);
+        assert!((gray("gray.hdr", None) - 0.5).abs() < 1e-2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 
-        Vec3::new(
-            rgb[0] as f32 / 255.0,
-            rgb[1] as f32 / 255.0,
-            rgb[2] as f32 / 255.0,
-        )
+    #[test]
+    fn test_empty_image() {
+        assert!(ImageTexture::new(Rgb32FImage::new(0, 0), ColorSpace::Linear).is_err());
+        assert!(ImageTexture::new(Rgb32FImage::new(4, 0), ColorSpace::Linear).is_err());
     }
 }
@@ -0,0 +1,127 @@
+//! Linear radiance -> display colors for 8-bit outputs
+
+use serde::Deserialize;
+
+use crate::math::{Real, Vec3};
+
+/// Compresses radiance in `[0, inf)` into `[0, 1]`, per channel
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum ToneMapper {
+    /// Values above 1 are clipped
+    #[default]
+    Clamp,
+    /// `c / (1 + c)`
+    Reinhard,
+    /// `c * (1 + c / white^2) / (1 + c)`, `white` and brighter map to 1
+    ReinhardExtended { white: Real },
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve
+    Aces,
+}
+
+impl ToneMapper {
+    pub fn map(&self, color: Vec3) -> Vec3 {
+        let color = color.max(Vec3::ZERO);
+        let mapped = match *self {
+            ToneMapper::Clamp => color,
+            ToneMapper::Reinhard => color / (1.0 + color),
+            ToneMapper::ReinhardExtended { white } => {
+                color * (1.0 + color / (white * white)) / (1.0 + color)
+            }
+            ToneMapper::Aces => {
+                const A: Real = 2.51;
+                const B: Real = 0.03;
+                const C: Real = 2.43;
+                const D: Real = 0.59;
+                const E: Real = 0.14;
+                (color * (A * color + B)) / (color * (C * color + D) + E)
+            }
+        };
+        mapped.clamp(Vec3::ZERO, Vec3::ONE)
+    }
+
+    /// `white` must be positive, there is nothing to map to 1 otherwise
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            ToneMapper::ReinhardExtended { white } => white > 0.0,
+            _ => true,
+        }
+    }
+}
+
+/// Exposure, tone mapping and sRGB encoding, in that order
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ToneMapping {
+    pub tone_mapper: ToneMapper,
+    /// Exposure value, every +1 doubles the radiance
+    pub exposure: Real,
+}
+
+impl ToneMapping {
+    /// Linear radiance -> sRGB encoded color in `[0, 1]`
+    pub fn apply(&self, linear: Vec3) -> Vec3 {
+        let exposed = linear * self.exposure.exp2();
+        self.tone_mapper.map(exposed).map(srgb_oetf)
+    }
+}
+
+/// The sRGB opto-electronic transfer function, linear `[0, 1]` -> encoded `[0, 1]`
+pub fn srgb_oetf(linear: Real) -> Real {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: Real, b: Real) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_tone_mappers() {
+        let map = |tone_mapper: ToneMapper, x: Real| tone_mapper.map(Vec3::splat(x)).x;
+
+        assert_close(map(ToneMapper::Clamp, 0.5), 0.5);
+        assert_close(map(ToneMapper::Clamp, 8.0), 1.0);
+        assert_close(map(ToneMapper::Clamp, -1.0), 0.0);
+
+        assert_close(map(ToneMapper::Reinhard, 1.0), 0.5);
+        assert_close(map(ToneMapper::Reinhard, 3.0), 0.75);
+
+        let extended = ToneMapper::ReinhardExtended { white: 4.0 };
+        assert_close(map(extended, 1.0), 0.53125);
+        assert_close(map(extended, 4.0), 1.0);
+        assert_close(map(extended, 100.0), 1.0);
+        assert!(extended.is_valid());
+        assert!(!ToneMapper::ReinhardExtended { white: 0.0 }.is_valid());
+        assert!(!ToneMapper::ReinhardExtended { white: Real::NAN }.is_valid());
+
+        assert_close(map(ToneMapper::Aces, 0.0), 0.0);
+        assert_close(map(ToneMapper::Aces, 1.0), 2.54 / 3.16);
+        assert_close(map(ToneMapper::Aces, 100.0), 1.0);
+    }
+
+    #[test]
+    fn test_tone_mapping() {
+        assert_close(srgb_oetf(0.0), 0.0);
+        assert_close(srgb_oetf(0.002), 0.02584);
+        assert_close(srgb_oetf(0.18), 0.46135);
+        assert_close(srgb_oetf(1.0), 1.0);
+
+        // +1 EV doubles, -2 EV quarters
+        let tone_mapping = ToneMapping {
+            tone_mapper: ToneMapper::Reinhard,
+            exposure: 1.0,
+        };
+        assert_close(tone_mapping.apply(Vec3::splat(0.5)).x, srgb_oetf(0.5));
+        let tone_mapping = ToneMapping {
+            tone_mapper: ToneMapper::Clamp,
+            exposure: -2.0,
+        };
+        assert_close(tone_mapping.apply(Vec3::splat(0.72)).x, srgb_oetf(0.18));
+    }
+}
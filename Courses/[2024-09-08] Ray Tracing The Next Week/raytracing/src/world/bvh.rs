@@ -1,145 +1,360 @@
 use std::ops::Range;
 
-use glam::Vec3;
+pub use rt_core::bvh::{Aabb, AabbHittable, BvhNode, BvhStats, HasAabb};
 
-use crate::{HitRecord, Hittable, Ray};
+use crate::{
+    math::{Real, Vec3},
+    HitRecord, Hittable, MaterialRef, Ray,
+};
 
-pub trait HasAabb {
-    fn aabb(&self) -> Aabb;
+/// A BVH built with the binned surface area heuristic (SAH), stored as a flat array of nodes
+/// in depth-first order
+pub struct Bvh {
+    nodes: Vec<LinearNode>,
+    /// ordered so that every leaf covers a contiguous range
+    objects: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
 }
 
-pub trait AabbHittable: Hittable + HasAabb {}
+struct LinearNode {
+    aabb: Aabb,
+    kind: LinearNodeKind,
+}
 
-impl<T: HasAabb + Hittable> AabbHittable for T {}
+enum LinearNodeKind {
+    Leaf {
+        first: usize,
+        count: usize,
+    },
+    /// the first child directly follows its parent
+    Interior {
+        second_child: usize,
+        axis: usize,
+    },
+}
 
-#[derive(Debug, Clone)]
-pub struct Aabb {
-    min: Vec3,
-    max: Vec3,
+/// What the builder needs to know about an object
+struct BuildObject {
+    index: usize,
+    aabb: Aabb,
+    centroid: Vec3,
 }
 
-impl Aabb {
-    pub fn new(min: Vec3, mut max: Vec3) -> Self {
-        const DELTA: f32 = 0.0001;
+impl Bvh {
+    const BINS: usize = 12;
+    const MAX_LEAF_OBJECTS: usize = 4;
+    /// Bounds the traversal stack, deeper nodes become leaves
+    const MAX_DEPTH: usize = 64;
+    /// Cost of visiting an inner node, relative to intersecting an object
+    const TRAVERSAL_COST: Real = 0.5;
 
-        if max.x - min.x < DELTA {
-            max.x += DELTA;
-        }
-        if max.y - min.y < DELTA {
-            max.y += DELTA;
-        }
-        if max.z - min.z < DELTA {
-            max.z += DELTA;
-        }
+    /// Panics if `objects` is empty.
+    pub fn from_objects(objects: Vec<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>) -> Self {
+        assert!(!objects.is_empty(), "a bvh needs at least one object");
 
-        Aabb { min, max }
-    }
+        let mut build_objects = objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let aabb = object.aabb();
+                BuildObject {
+                    index,
+                    centroid: aabb.centroid(),
+                    aabb,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut nodes = Vec::with_capacity(2 * objects.len());
+        Self::build(&mut nodes, &mut build_objects, 0, 1);
+
+        // reorder the objects to match the leaves
+        let mut objects = objects.into_iter().map(Some).collect::<Vec<_>>();
+        let objects = build_objects
+            .iter()
+            .map(|object| objects[object.index].take().unwrap())
+            .collect();
 
-    pub fn union(&self, other: &Aabb) -> Aabb {
-        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+        Bvh { nodes, objects }
     }
 
-    pub fn longest_axis(&self) -> usize {
-        let x = self.max.x - self.min.x;
-        let y = self.max.y - self.min.y;
-        let z = self.max.z - self.min.z;
-        let max = x.max(y).max(z);
+    /// Appends the subtree over `objects`, which start at `offset` in the final order
+    fn build(
+        nodes: &mut Vec<LinearNode>,
+        objects: &mut [BuildObject],
+        offset: usize,
+        depth: usize,
+    ) {
+        let aabb = objects
+            .iter()
+            .map(|object| object.aabb.clone())
+            .reduce(|a, b| a.union(&b))
+            .unwrap();
+        let leaf = LinearNode {
+            aabb: aabb.clone(),
+            kind: LinearNodeKind::Leaf {
+                first: offset,
+                count: objects.len(),
+            },
+        };
+        if objects.len() == 1 || depth >= Self::MAX_DEPTH {
+            nodes.push(leaf);
+            return;
+        }
+
+        let (axis, split) = match Self::find_split(objects, &aabb) {
+            Some((axis, split, cost))
+                if cost < objects.len() as Real || objects.len() > Self::MAX_LEAF_OBJECTS =>
+            {
+                (axis, split)
+            }
+            // splitting would not pay off, or every centroid is at the same spot
+            _ if objects.len() <= Self::MAX_LEAF_OBJECTS => {
+                nodes.push(leaf);
+                return;
+            }
+            _ => {
+                let axis = aabb.longest_axis();
+                objects.sort_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+                (axis, objects.len() / 2)
+            }
+        };
 
-        let arr = [x, y, z];
-        arr.iter().position(|&x| x == max).unwrap()
+        let index = nodes.len();
+        nodes.push(leaf);
+        let (left, right) = objects.split_at_mut(split);
+        Self::build(nodes, left, offset, depth + 1);
+        let second_child = nodes.len();
+        Self::build(nodes, right, offset + split, depth + 1);
+        nodes[index].kind = LinearNodeKind::Interior { second_child, axis };
     }
-}
 
-impl Hittable for Aabb {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
-        let mut t_min = t_range.start;
-        let mut t_max = t_range.end;
+    /// Finds the cheapest split among bins of the centroids, partitions `objects` by it and
+    /// returns `(axis, number of objects on the left, cost)`
+    fn find_split(objects: &mut [BuildObject], aabb: &Aabb) -> Option<(usize, usize, Real)> {
+        let (centroid_min, centroid_max) = objects.iter().fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), object| (min.min(object.centroid), max.max(object.centroid)),
+        );
+        let extent = centroid_max - centroid_min;
+        let bin_of = |axis: usize, centroid: Vec3| {
+            let t = (centroid[axis] - centroid_min[axis]) / extent[axis];
+            ((t * Self::BINS as Real) as usize).min(Self::BINS - 1)
+        };
 
-        for i in 0..3 {
-            let inv_d = 1.0 / ray.direction[i];
-            let t0 = (self.min[i] - ray.origin[i]) * inv_d;
-            let t1 = (self.max[i] - ray.origin[i]) * inv_d;
+        let mut best: Option<(usize, usize, Real)> = None;
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
 
-            t_min = t_min.max(t0.min(t1));
-            t_max = t_max.min(t0.max(t1));
+            let mut bins: [(Option<Aabb>, usize); Self::BINS] = Default::default();
+            for object in objects.iter() {
+                let (bin_aabb, count) = &mut bins[bin_of(axis, object.centroid)];
+                *bin_aabb = Some(match bin_aabb {
+                    Some(bin_aabb) => bin_aabb.union(&object.aabb),
+                    None => object.aabb.clone(),
+                });
+                *count += 1;
+            }
 
-            // println!("t_min: {}, t_max: {}", t_min, t_max);
+            // area * count of everything right of each bin boundary, swept from the right
+            let mut right_costs = [0.0; Self::BINS];
+            let mut right: Option<Aabb> = None;
+            let mut right_count = 0;
+            for i in (1..Self::BINS).rev() {
+                right = union(right, &bins[i].0);
+                right_count += bins[i].1;
+                right_costs[i] =
+                    right.as_ref().map_or(0.0, Aabb::surface_area) * right_count as Real;
+            }
 
-            if t_max <= t_min {
-                return None;
+            let mut left: Option<Aabb> = None;
+            let mut left_count = 0;
+            for i in 1..Self::BINS {
+                left = union(left, &bins[i - 1].0);
+                left_count += bins[i - 1].1;
+                if left_count == 0 || left_count == objects.len() {
+                    continue;
+                }
+                let left_cost = left.as_ref().map_or(0.0, Aabb::surface_area) * left_count as Real;
+                let cost =
+                    Self::TRAVERSAL_COST + (left_cost + right_costs[i]) / aabb.surface_area();
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, i, cost));
+                }
             }
         }
 
-        let point = ray.at(t_min);
-        Some(HitRecord {
-            point,
-            normal: Vec3::ZERO,
-            t: t_min,
-            front_face: false,
-            material: None,
-            u: 0.0,
-            v: 0.0,
-        })
+        let (axis, bin, cost) = best?;
+        let mut split = 0;
+        for i in 0..objects.len() {
+            if bin_of(axis, objects[i].centroid) < bin {
+                objects.swap(i, split);
+                split += 1;
+            }
+        }
+        Some((axis, split, cost))
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        fn depth(nodes: &[LinearNode], index: usize) -> usize {
+            match nodes[index].kind {
+                LinearNodeKind::Leaf { .. } => 1,
+                LinearNodeKind::Interior { second_child, .. } => {
+                    depth(nodes, index + 1).max(depth(nodes, second_child)) + 1
+                }
+            }
+        }
+
+        BvhStats {
+            leaves: self.objects.len(),
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|node| matches!(node.kind, LinearNodeKind::Interior { .. }))
+                .count(),
+            depth: depth(&self.nodes, 0),
+        }
     }
 }
 
-pub enum BvhNode {
-    Leaf(Box<dyn AabbHittable + Send + Sync>),
-    Node {
-        left: Box<BvhNode>,
-        right: Box<BvhNode>,
-        aabb: Aabb,
-    },
+fn union(aabb: Option<Aabb>, other: &Option<Aabb>) -> Option<Aabb> {
+    match (aabb, other) {
+        (Some(aabb), Some(other)) => Some(aabb.union(other)),
+        (aabb, other) => aabb.or(other.clone()),
+    }
 }
 
-impl Hittable for BvhNode {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
-        match self {
-            BvhNode::Leaf(object) => object.hit(ray, t_range),
-            BvhNode::Node { left, right, aabb } => {
-                if aabb.hit(ray, t_range.clone()).is_none() {
-                    return None;
+impl Hittable<MaterialRef> for Bvh {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        let mut closest: Option<HitRecord> = None;
+        let mut t_max = t_range.end;
+
+        let mut stack = [0; Self::MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut index = 0;
+        let inv_direction = ray.inv_direction();
+        loop {
+            let node = &self.nodes[index];
+            if node
+                .aabb
+                .intersects(ray, inv_direction, t_range.start..t_max)
+                .is_some()
+            {
+                match node.kind {
+                    LinearNodeKind::Leaf { first, count } => {
+                        for object in &self.objects[first..first + count] {
+                            if let Some(record) = object.hit(ray, t_range.start..t_max) {
+                                t_max = record.t;
+                                closest = Some(record);
+                            }
+                        }
+                    }
+                    LinearNodeKind::Interior { second_child, axis } => {
+                        // visit the child nearer to the ray's origin first, so that its hits
+                        // shorten the range for the other one
+                        let (near, far) = if ray.direction[axis] < 0.0 {
+                            (second_child, index + 1)
+                        } else {
+                            (index + 1, second_child)
+                        };
+                        stack[stack_len] = far;
+                        stack_len += 1;
+                        index = near;
+                        continue;
+                    }
                 }
-                let hit_left = left.hit(ray, t_range.clone());
-                let hit_right = right.hit(
-                    ray,
-                    t_range.start..hit_left.as_ref().map(|rec| rec.t).unwrap_or(t_range.end),
-                );
-                hit_right.or(hit_left)
             }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            index = stack[stack_len];
         }
+
+        closest
     }
 }
 
-impl HasAabb for BvhNode {
+impl HasAabb for Bvh {
     fn aabb(&self) -> Aabb {
-        match self {
-            BvhNode::Leaf(object) => object.aabb(),
-            BvhNode::Node { aabb, .. } => aabb.clone(),
-        }
+        self.nodes[0].aabb.clone()
     }
 }
 
-impl BvhNode {
-    pub fn from_objects(mut objects: Vec<Box<dyn AabbHittable + Send + Sync>>) -> Self {
-        let aabb = objects
-            .iter()
-            .map(|obj| obj.aabb())
-            .reduce(|a, b| a.union(&b))
-            .unwrap();
-        let axis = aabb.longest_axis();
-        objects.sort_by(|a, b| a.aabb().min[axis].partial_cmp(&b.aabb().min[axis]).unwrap());
-
-        return if objects.len() == 1 {
-            let object = objects.remove(0);
-            BvhNode::Leaf(object)
-        } else {
-            let left = Box::new(BvhNode::from_objects(
-                objects.drain(..objects.len() / 2).collect(),
-            ));
-            let right = Box::new(BvhNode::from_objects(objects));
-            // let aabb = left.aabb().union(&right.aabb());
-            BvhNode::Node { left, right, aabb }
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{material::Lambertian, primitive::Sphere, texture::SolidColor, world::list::List};
+
+    use super::*;
+
+    #[test]
+    fn test_sah_bvh() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let material: Arc<Box<dyn crate::material::Material + Send + Sync>> = Arc::new(Box::new(
+            Lambertian::new(Arc::new(Box::new(SolidColor::new(Vec3::ONE)))),
+        ));
+        let spheres = || {
+            let mut rng = StdRng::seed_from_u64(1);
+            (0..500)
+                .map(|_| {
+                    let center = Vec3::new(
+                        rng.gen_range(-10.0..10.0),
+                        rng.gen_range(-10.0..10.0),
+                        rng.gen_range(-10.0..10.0),
+                    );
+                    Box::new(Sphere::new(
+                        center,
+                        rng.gen_range(0.05..0.5),
+                        material.clone(),
+                    ))
+                })
+                .collect::<Vec<_>>()
         };
+
+        let bvh = Bvh::from_objects(
+            spheres()
+                .into_iter()
+                .map(|sphere| sphere as Box<dyn AabbHittable<MaterialRef> + Send + Sync>)
+                .collect(),
+        );
+        let list = List::from_objects(
+            spheres()
+                .into_iter()
+                .map(|sphere| sphere as Box<dyn Hittable<MaterialRef> + Send + Sync>)
+                .collect(),
+        );
+
+        let stats = bvh.stats();
+        assert_eq!(stats.leaves, 500);
+        assert!(stats.depth < 30, "{stats:?}");
+        for node in &bvh.nodes {
+            if let LinearNodeKind::Leaf { count, .. } = node.kind {
+                assert!(count <= Bvh::MAX_LEAF_OBJECTS);
+            }
+        }
+
+        // the same closest hits as testing every sphere
+        for _ in 0..1000 {
+            let origin = Vec3::new(
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(-15.0..15.0),
+            );
+            let target = Vec3::new(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+            );
+            let ray = Ray::new(origin, target - origin);
+            let expected = list.hit(&ray, 0.001..Real::INFINITY).map(|record| record.t);
+            let actual = bvh.hit(&ray, 0.001..Real::INFINITY).map(|record| record.t);
+            assert_eq!(actual, expected);
+        }
     }
 }
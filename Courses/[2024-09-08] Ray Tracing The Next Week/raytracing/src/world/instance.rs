@@ -0,0 +1,283 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    math::{Affine3, Mat3, Quat, Real, Vec3},
+    world::bvh::{Aabb, AabbHittable, HasAabb},
+    HitRecord, Hittable, MaterialRef, Ray,
+};
+
+/// Moves an object by `offset` without touching its geometry
+pub struct Translate {
+    object: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
+    offset: Vec3,
+
+    /// cached value
+    aabb: Aabb,
+}
+
+impl Translate {
+    pub fn new(
+        object: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
+        offset: Vec3,
+    ) -> Self {
+        let aabb = object.aabb();
+        let aabb = Aabb::new(aabb.min() + offset, aabb.max() + offset);
+        Translate {
+            object,
+            offset,
+            aabb,
+        }
+    }
+}
+
+impl Hittable<MaterialRef> for Translate {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        // move the ray into object space instead of moving the object
+        let offset_ray = Ray::new(ray.origin - self.offset, ray.direction)
+            .with_time(ray.time)
+            .with_spread(ray.spread);
+
+        let mut record = self.object.hit(&offset_ray, t_range)?;
+        record.point += self.offset;
+        Some(record)
+    }
+}
+
+impl HasAabb for Translate {
+    fn aabb(&self) -> Aabb {
+        self.aabb.clone()
+    }
+}
+
+/// Rotates an object around the y axis by `angle` degrees
+pub struct RotateY {
+    object: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
+
+    /// followings are cached values
+    sin_theta: Real,
+    cos_theta: Real,
+    aabb: Aabb,
+}
+
+impl RotateY {
+    pub fn new(object: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>, angle: Real) -> Self {
+        let (sin_theta, cos_theta) = angle.to_radians().sin_cos();
+
+        let aabb = Aabb::from_points(object.aabb().corners().map(|p| {
+            Vec3::new(
+                cos_theta * p.x + sin_theta * p.z,
+                p.y,
+                -sin_theta * p.x + cos_theta * p.z,
+            )
+        }));
+
+        RotateY {
+            object,
+            sin_theta,
+            cos_theta,
+            aabb,
+        }
+    }
+
+    /// world space -> object space
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x - self.sin_theta * v.z,
+            v.y,
+            self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    /// object space -> world space
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x + self.sin_theta * v.z,
+            v.y,
+            -self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+}
+
+impl Hittable<MaterialRef> for RotateY {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        let rotated_ray = Ray::new(self.to_object(ray.origin), self.to_object(ray.direction))
+            .with_time(ray.time)
+            .with_spread(ray.spread);
+
+        let mut record = self.object.hit(&rotated_ray, t_range)?;
+        record.point = self.to_world(record.point);
+        record.normal = self.to_world(record.normal);
+        record.tangent = self.to_world(record.tangent);
+        record.bitangent = self.to_world(record.bitangent);
+        Some(record)
+    }
+}
+
+impl HasAabb for RotateY {
+    fn aabb(&self) -> Aabb {
+        self.aabb.clone()
+    }
+}
+
+/// A general affine transform (object space -> world space)
+///
+/// Transforms compose in call order, e.g.
+/// `Transform::new(object).scale(Vec3::splat(2.0)).rotate_y(45.0).translate(offset)`
+/// scales first, then rotates, then translates.
+pub struct Transform {
+    object: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
+    transform: Affine3,
+
+    /// followings are cached values
+    inverse: Affine3,
+    normal_matrix: Mat3,
+    aabb: Aabb,
+}
+
+impl Transform {
+    pub fn new(object: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>) -> Self {
+        Self::from_affine(object, Affine3::IDENTITY)
+    }
+
+    pub fn from_affine(
+        object: Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>>,
+        transform: Affine3,
+    ) -> Self {
+        let inverse = transform.inverse();
+        // normals are transformed by the inverse transpose to stay perpendicular
+        // to the surface under non-uniform scaling
+        let normal_matrix = inverse.matrix3.transpose();
+        let aabb = Aabb::from_points(
+            object
+                .aabb()
+                .corners()
+                .map(|p| transform.transform_point3(p)),
+        );
+
+        Transform {
+            object,
+            transform,
+            inverse,
+            normal_matrix,
+            aabb,
+        }
+    }
+
+    pub fn then(self, transform: Affine3) -> Self {
+        Self::from_affine(self.object, transform * self.transform)
+    }
+
+    pub fn translate(self, offset: Vec3) -> Self {
+        self.then(Affine3::from_translation(offset))
+    }
+
+    /// Rotates around `axis` by `angle` degrees
+    pub fn rotate(self, axis: Vec3, angle: Real) -> Self {
+        self.then(Affine3::from_quat(Quat::from_axis_angle(
+            axis.normalize(),
+            angle.to_radians(),
+        )))
+    }
+
+    pub fn rotate_y(self, angle: Real) -> Self {
+        self.rotate(Vec3::Y, angle)
+    }
+
+    pub fn scale(self, scale: Vec3) -> Self {
+        self.then(Affine3::from_scale(scale))
+    }
+}
+
+impl Hittable<MaterialRef> for Transform {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        // the direction is not normalized, so `t` is the same in both spaces, and the
+        // footprint is measured in object space like the uv coordinates are
+        let object_ray = Ray::new(
+            self.inverse.transform_point3(ray.origin),
+            self.inverse.transform_vector3(ray.direction),
+        )
+        .with_time(ray.time)
+        .with_spread(ray.spread);
+
+        let mut record = self.object.hit(&object_ray, t_range)?;
+        record.point = self.transform.transform_point3(record.point);
+        record.normal = (self.normal_matrix * record.normal).normalize();
+        // tangents lie in the surface and transform like any other direction
+        let to_world = |v| self.transform.transform_vector3(v).normalize_or_zero();
+        record.tangent = to_world(record.tangent);
+        record.bitangent = to_world(record.bitangent);
+        Some(record)
+    }
+}
+
+impl HasAabb for Transform {
+    fn aabb(&self) -> Aabb {
+        self.aabb.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{material::Lambertian, primitive::Sphere, texture::SolidColor};
+
+    use super::*;
+
+    fn unit_sphere() -> Arc<Box<dyn AabbHittable<MaterialRef> + Send + Sync>> {
+        Arc::new(Box::new(Sphere::new(
+            Vec3::ZERO,
+            1.0,
+            Arc::new(Box::new(Lambertian::new(Arc::new(Box::new(
+                SolidColor::new(Vec3::ONE),
+            ))))),
+        )))
+    }
+
+    #[test]
+    fn test_translate() {
+        let sphere = Translate::new(unit_sphere(), Vec3::new(5.0, 0.0, 0.0));
+        assert!(sphere
+            .aabb()
+            .min()
+            .abs_diff_eq(Vec3::new(4.0, -1.0, -1.0), 1e-4));
+
+        let ray = Ray::new(Vec3::new(5.0, 0.0, 5.0), Vec3::NEG_Z);
+        let record = sphere.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!(record.point.abs_diff_eq(Vec3::new(5.0, 0.0, 1.0), 1e-4));
+        assert!(record.normal.abs_diff_eq(Vec3::Z, 1e-4));
+    }
+
+    #[test]
+    fn test_rotate_y_matches_transform() {
+        let rotated = RotateY::new(unit_sphere(), 30.0);
+        let transformed = Transform::new(unit_sphere()).rotate_y(30.0);
+
+        let ray = Ray::new(Vec3::new(0.3, 0.2, 5.0), Vec3::NEG_Z);
+        let a = rotated.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        let b = transformed.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!((a.t - b.t).abs() < 1e-4);
+        assert!(a.normal.abs_diff_eq(b.normal, 1e-4));
+    }
+
+    #[test]
+    fn test_non_uniform_scale() {
+        // an ellipsoid stretched along x
+        let ellipsoid = Transform::new(unit_sphere())
+            .scale(Vec3::new(4.0, 1.0, 1.0))
+            .translate(Vec3::Y);
+        let aabb = ellipsoid.aabb();
+        assert!(aabb.min().abs_diff_eq(Vec3::new(-4.0, 0.0, -1.0), 1e-4));
+        assert!(aabb.max().abs_diff_eq(Vec3::new(4.0, 2.0, 1.0), 1e-4));
+
+        let ray = Ray::new(Vec3::new(10.0, 1.0, 0.0), Vec3::NEG_X);
+        let record = ellipsoid.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        assert!(record.point.abs_diff_eq(Vec3::new(4.0, 1.0, 0.0), 1e-4));
+        assert!(record.normal.abs_diff_eq(Vec3::X, 1e-4));
+
+        // off-axis hit, the normal must stay perpendicular to the surface
+        let ray = Ray::new(Vec3::new(2.0, 10.0, 0.0), Vec3::NEG_Y);
+        let record = ellipsoid.hit(&ray, 0.0..Real::INFINITY).unwrap();
+        let p = record.point - Vec3::Y;
+        let expected = Vec3::new(p.x / 16.0, p.y, p.z).normalize();
+        assert!(record.normal.abs_diff_eq(expected, 1e-4));
+    }
+}
@@ -1,25 +1,58 @@
-use std::ops::Range;
+use std::{
+    hash::{Hash, Hasher},
+    ops::Range,
+};
 
-use crate::{Hittable, HitRecord, Ray};
+use rand::{Rng, RngCore};
 
-pub struct List(pub(super) Vec<Box<dyn Hittable + Send + Sync>>);
+use crate::{
+    math::{Real, Vec3},
+    HitRecord, Hittable, MaterialRef, Ray, Sampleable,
+};
+
+pub struct List(pub(super) Vec<Box<dyn Hittable<MaterialRef> + Send + Sync>>);
 
 impl List {
-    pub fn from_objects(objects: Vec<Box<dyn Hittable + Send + Sync>>) -> Self {
+    pub fn from_objects(objects: Vec<Box<dyn Hittable<MaterialRef> + Send + Sync>>) -> Self {
         List(objects)
     }
 }
 
-impl Hittable for List {
-    fn hit(&self, ray: &Ray, t_range: Range<f32>) -> Option<HitRecord> {
-        let mut closest = t_range.end;
-        let mut hit_record = None;
-        for object in self.0.iter() {
-            if let Some(record) = object.hit(ray, t_range.start..closest) {
-                closest = record.t;
-                hit_record = Some(record);
-            }
+impl Hittable<MaterialRef> for List {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        self.0.hit(ray, t_range)
+    }
+}
+
+/// Several lights sampled as one, never empty
+pub struct Lights(Vec<Box<dyn Sampleable + Send + Sync>>);
+
+impl Lights {
+    /// `None` when there are no lights, as there would be nothing to sample
+    pub fn new(lights: Vec<Box<dyn Sampleable + Send + Sync>>) -> Option<Self> {
+        (!lights.is_empty()).then_some(Lights(lights))
+    }
+}
+
+impl Sampleable for Lights {
+    /// The average density of the lights, as [`Lights::random`] picks one uniformly
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, time: Real) -> Real {
+        let weight = 1.0 / self.0.len() as Real;
+        self.0
+            .iter()
+            .map(|light| weight * light.pdf_value(origin, direction, time))
+            .sum()
+    }
+
+    fn random(&self, origin: Vec3, time: Real, rng: &mut dyn RngCore) -> Vec3 {
+        let index = rng.gen_range(0..self.0.len());
+        self.0[index].random(origin, time, rng)
+    }
+
+    fn hash_settings(&self, mut state: &mut dyn Hasher) {
+        self.0.len().hash(&mut state);
+        for light in &self.0 {
+            light.hash_settings(state);
         }
-        hit_record
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,69 @@
+//! The median split [`BvhNode`] against testing every sphere of the final scene of the first
+//! book, both books build their worlds from the same [`final_scene`]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rt_core::{
+    bvh::{AabbHittable, BvhNode},
+    math::{Real, Vec3},
+    scene::{final_scene, SphereDesc},
+    Hittable, Ray,
+};
+
+fn spheres() -> Vec<SphereDesc> {
+    final_scene(&mut StdRng::seed_from_u64(0))
+}
+
+fn objects() -> Vec<Box<dyn AabbHittable + Send + Sync>> {
+    spheres()
+        .into_iter()
+        .map(|sphere| Box::new(sphere) as Box<dyn AabbHittable + Send + Sync>)
+        .collect()
+}
+
+fn build(c: &mut Criterion) {
+    c.bench_function("build BvhNode", |b| {
+        b.iter_with_setup(objects, BvhNode::from_objects)
+    });
+}
+
+/// Rays from around the camera of the final scene into the spheres
+fn rays() -> Vec<Ray> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..10_000)
+        .map(|_| {
+            let origin =
+                Vec3::new(13.0, 2.0, 3.0) + 0.5 * Vec3::new(rng.gen(), rng.gen(), rng.gen());
+            let target = Vec3::new(
+                rng.gen_range(-11.0..11.0),
+                rng.gen_range(0.0..1.0),
+                rng.gen_range(-11.0..11.0),
+            );
+            Ray::new(origin, target - origin)
+        })
+        .collect()
+}
+
+fn trace(c: &mut Criterion) {
+    let rays = rays();
+    let trace_all = |world: &dyn Hittable| {
+        rays.iter()
+            .filter(|ray| world.hit(ray, 0.001..Real::INFINITY).is_some())
+            .count()
+    };
+
+    let list: Vec<Box<dyn Hittable + Send + Sync>> = spheres()
+        .into_iter()
+        .map(|sphere| Box::new(sphere) as Box<dyn Hittable + Send + Sync>)
+        .collect();
+    let bvh_node = BvhNode::from_objects(objects());
+    assert_eq!(trace_all(&list), trace_all(&bvh_node));
+
+    let mut group = c.benchmark_group("trace 10k rays");
+    group.bench_function("every sphere", |b| b.iter(|| trace_all(black_box(&list))));
+    group.bench_function("BvhNode", |b| b.iter(|| trace_all(black_box(&bvh_node))));
+    group.finish();
+}
+
+criterion_group!(benches, build, trace);
+criterion_main!(benches);
@@ -0,0 +1,269 @@
+use std::ops::Range;
+
+use crate::{
+    math::{Real, Vec3},
+    HitRecord, Hittable, Ray,
+};
+
+/// `1 + 2 * gamma(3)`, widens the far side of slab tests so that rounding errors never miss
+/// a box (Ize, "Robust BVH Ray Traversal")
+const ROBUST_FAR: Real =
+    1.0 + 2.0 * (3.0 * Real::EPSILON / 2.0) / (1.0 - 3.0 * Real::EPSILON / 2.0);
+
+pub trait HasAabb {
+    fn aabb(&self) -> Aabb;
+}
+
+/// `M` is whatever the objects hit with, as in [`Hittable`]
+pub trait AabbHittable<M = ()>: Hittable<M> + HasAabb {}
+
+impl<M, T: HasAabb + Hittable<M>> AabbHittable<M> for T {}
+
+#[derive(Debug, Clone)]
+pub struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    /// `min` and `max` may be any two opposite corners, the slab test in
+    /// [`Aabb::intersects`] needs them sorted
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        const DELTA: Real = 0.0001;
+
+        let (min, mut max) = (min.min(max), min.max(max));
+
+        if max.x - min.x < DELTA {
+            max.x += DELTA;
+        }
+        if max.y - min.y < DELTA {
+            max.y += DELTA;
+        }
+        if max.z - min.z < DELTA {
+            max.z += DELTA;
+        }
+
+        Aabb { min, max }
+    }
+
+    pub fn min(&self) -> Vec3 {
+        self.min
+    }
+
+    pub fn max(&self) -> Vec3 {
+        self.max
+    }
+
+    /// The 8 corner points of the box
+    pub fn corners(&self) -> [Vec3; 8] {
+        [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+            Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            )
+        })
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Aabb {
+        let (min, max) = points
+            .into_iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), p| {
+                (min.min(p), max.max(p))
+            });
+        Aabb::new(min, max)
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn surface_area(&self) -> Real {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let x = self.max.x - self.min.x;
+        let y = self.max.y - self.min.y;
+        let z = self.max.z - self.min.z;
+        let max = x.max(y).max(z);
+
+        let arr = [x, y, z];
+        arr.iter().position(|&x| x == max).unwrap()
+    }
+}
+
+impl Aabb {
+    /// Slab test, returns the part of `t_range` in which the ray is inside the box
+    ///
+    /// `inv_dir` is `1 / ray.direction`, usually [`Ray::inv_direction`]. A zero direction
+    /// component makes its slab test `0 * inf = NaN` when the origin lies on the slab's plane,
+    /// which is ignored, so rays grazing a face count as hits.
+    pub fn intersects(
+        &self,
+        ray: &Ray,
+        inv_dir: Vec3,
+        t_range: Range<Real>,
+    ) -> Option<(Real, Real)> {
+        let bounds = [self.min, self.max];
+        let sign = ray.sign();
+        let mut t_min = t_range.start;
+        let mut t_max = t_range.end;
+
+        for i in 0..3 {
+            let t_near = (bounds[sign[i]][i] - ray.origin[i]) * inv_dir[i];
+            let t_far = (bounds[1 - sign[i]][i] - ray.origin[i]) * inv_dir[i] * ROBUST_FAR;
+
+            // `Real::max` and `Real::min` return the other operand for NaN
+            t_min = t_min.max(t_near);
+            t_max = t_max.min(t_far);
+        }
+
+        (t_min <= t_max).then_some((t_min, t_max))
+    }
+}
+
+/// Shape of a [`BvhNode`] tree
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BvhStats {
+    /// objects in the tree, a mesh or an instance counts as one
+    pub leaves: usize,
+    /// inner nodes
+    pub nodes: usize,
+    pub depth: usize,
+}
+
+/// A BVH built by splitting at the median along the longest axis
+pub enum BvhNode<M = ()> {
+    Leaf(Box<dyn AabbHittable<M> + Send + Sync>),
+    Node {
+        left: Box<BvhNode<M>>,
+        right: Box<BvhNode<M>>,
+        aabb: Aabb,
+    },
+}
+
+impl<M> Hittable<M> for BvhNode<M> {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord<M>> {
+        match self {
+            BvhNode::Leaf(object) => object.hit(ray, t_range),
+            BvhNode::Node { left, right, aabb } => {
+                aabb.intersects(ray, ray.inv_direction(), t_range.clone())?;
+                let hit_left = left.hit(ray, t_range.clone());
+                let hit_right = right.hit(
+                    ray,
+                    t_range.start..hit_left.as_ref().map(|rec| rec.t).unwrap_or(t_range.end),
+                );
+                hit_right.or(hit_left)
+            }
+        }
+    }
+}
+
+impl<M> HasAabb for BvhNode<M> {
+    fn aabb(&self) -> Aabb {
+        match self {
+            BvhNode::Leaf(object) => object.aabb(),
+            BvhNode::Node { aabb, .. } => aabb.clone(),
+        }
+    }
+}
+
+impl<M> BvhNode<M> {
+    /// Panics if `objects` is empty.
+    pub fn from_objects(objects: Vec<Box<dyn AabbHittable<M> + Send + Sync>>) -> Self {
+        let objects = objects
+            .into_iter()
+            .map(|object| (object.aabb(), object))
+            .collect();
+        Self::build(objects)
+    }
+
+    fn build(mut objects: Vec<(Aabb, Box<dyn AabbHittable<M> + Send + Sync>)>) -> Self {
+        let aabb = objects
+            .iter()
+            .map(|(aabb, _)| aabb.clone())
+            .reduce(|a, b| a.union(&b))
+            .expect("a bvh needs at least one object");
+
+        if objects.len() == 1 {
+            let (_, object) = objects.remove(0);
+            return BvhNode::Leaf(object);
+        }
+
+        let axis = aabb.longest_axis();
+        objects.sort_by(|(a, _), (b, _)| a.min[axis].total_cmp(&b.min[axis]));
+        let right = objects.split_off(objects.len() / 2);
+        BvhNode::Node {
+            left: Box::new(Self::build(objects)),
+            right: Box::new(Self::build(right)),
+            aabb,
+        }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        match self {
+            BvhNode::Leaf(_) => BvhStats {
+                leaves: 1,
+                nodes: 0,
+                depth: 1,
+            },
+            BvhNode::Node { left, right, .. } => {
+                let left = left.stats();
+                let right = right.stats();
+                BvhStats {
+                    leaves: left.leaves + right.leaves,
+                    nodes: left.nodes + right.nodes + 1,
+                    depth: left.depth.max(right.depth) + 1,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_aabb_intersects() {
+        let aabb = Aabb::new(Vec3::ZERO, Vec3::ONE);
+        let check = |origin: Vec3, direction: Vec3, expected: Option<(Real, Real)>| {
+            let ray = Ray::new(origin, direction);
+            let range = aabb.intersects(&ray, ray.inv_direction(), 0.0..Real::INFINITY);
+            match (range, expected) {
+                (Some((t_min, t_max)), Some((min, max))) => {
+                    assert!((t_min - min).abs() < 1e-5 && (t_max - max).abs() < 1e-5)
+                }
+                _ => assert_eq!(range, expected),
+            }
+        };
+
+        // axis parallel, both ways and with a negative zero
+        check(Vec3::new(0.5, 0.5, -1.0), Vec3::Z, Some((1.0, 2.0)));
+        check(Vec3::new(0.5, 0.5, 2.0), Vec3::NEG_Z, Some((1.0, 2.0)));
+        check(Vec3::new(0.5, 0.5, 2.0), Vec3::Z, None);
+        check(Vec3::new(1.5, 0.5, -1.0), Vec3::Z, None);
+        let direction = Vec3::new(-0.0, 0.0, 1.0);
+        check(Vec3::new(0.5, 0.5, -1.0), direction, Some((1.0, 2.0)));
+        check(Vec3::new(-0.5, 0.5, -1.0), direction, None);
+
+        // grazing a face and an edge, the origins lie on slab planes
+        check(Vec3::new(0.0, 0.5, -1.0), Vec3::Z, Some((1.0, 2.0)));
+        check(Vec3::new(1.0, 1.0, -1.0), Vec3::Z, Some((1.0, 2.0)));
+        check(Vec3::new(1.001, 0.5, -1.0), Vec3::Z, None);
+
+        // touching a corner
+        let direction = Vec3::new(1.0, 1.0, 0.0);
+        check(Vec3::new(-1.0, 0.0, 0.5), direction, Some((1.0, 1.0)));
+        check(Vec3::new(-1.0, 0.01, 0.5), direction, None);
+
+        let ray = Ray::new(Vec3::new(0.5, 0.5, -1.0), Vec3::Z);
+        assert_eq!(aabb.intersects(&ray, ray.inv_direction(), 0.0..0.5), None);
+    }
+}
@@ -0,0 +1,410 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{
+    fmt,
+    fs::File,
+    hash::{Hash, Hasher},
+    io::BufWriter,
+    path::Path,
+    time::Instant,
+};
+
+use crate::{
+    log::logger,
+    math::{Real, Vec3},
+    utils::{linear_to_gamma, random_in_unit_disk},
+    Ray,
+};
+use ::log::{debug, info};
+use image::{
+    codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding},
+    ExtendedColorType, ImageEncoder, ImageResult, Rgb, RgbImage,
+};
+use indicatif::ProgressBar;
+use rand::{thread_rng, Rng, RngCore};
+
+pub struct Camera {
+    // focal_length: Real,
+    fov: Real,
+    aspect_ratio: Real,
+
+    pos: Vec3,
+    look_at: Vec3,
+    up: Vec3,
+
+    samples_per_pixel: u32,
+    max_depth: u32,
+    defocus_angle: Real,
+    focus_distance: Real,
+
+    /// Whether [`Camera::render_to_path`] writes gamma 2 colors instead of linear ones
+    gamma_correction: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        let aspect_ratio = 16.0 / 9.0;
+        // let focal_length = 1.0;
+        let fov: Real = 90.0;
+
+        let pos = Vec3::ZERO;
+        let look_at = Vec3::NEG_Z;
+        let up = Vec3::Y;
+
+        let defocus_angle = 0.0;
+        let focus_distance = 10.0;
+
+        Self {
+            aspect_ratio,
+            // focal_length,
+            fov,
+            pos,
+            look_at,
+            up,
+            defocus_angle,
+            focus_distance,
+
+            samples_per_pixel: 100,
+            max_depth: 50,
+            gamma_correction: true,
+        }
+    }
+}
+
+impl Camera {
+    pub fn new(aspect_ratio: Real) -> Self {
+        Camera {
+            aspect_ratio,
+            ..Default::default()
+        }
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: Real) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn pos(mut self, pos: Vec3) -> Self {
+        self.pos = pos;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Vec3) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn up(mut self, up: Vec3) -> Self {
+        self.up = up.normalize();
+        self
+    }
+
+    pub fn defocus_angle(mut self, defocus_angle: Real) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    pub fn focus_distance(mut self, focus_distance: Real) -> Self {
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    pub fn focus_to(mut self, target: Vec3) -> Self {
+        self.look_at = target;
+        self.focus_distance = self.pos.distance(target);
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: u32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn fov(mut self, fov: Real) -> Self {
+        self.fov = fov;
+        self
+    }
+
+    pub fn gamma_correction(mut self, gamma_correction: bool) -> Self {
+        self.gamma_correction = gamma_correction;
+        self
+    }
+
+    pub fn set_fov(&mut self, fov: Real) -> &mut Self {
+        self.fov = fov;
+        self
+    }
+
+    pub fn set_defocus_angle(&mut self, defocus_angle: Real) -> &mut Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    pub fn set_focus_distance(&mut self, focus_distance: Real) -> &mut Self {
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    pub fn set_look_at(&mut self, look_at: Vec3) -> &mut Self {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn set_up(&mut self, up: Vec3) -> &mut Self {
+        self.up = up.normalize();
+        self
+    }
+
+    pub fn focus_point(&self) -> Vec3 {
+        self.pos + self.focus_distance * (self.look_at - self.pos).normalize()
+    }
+
+    pub fn output_height(&self, output_width: u32) -> u32 {
+        (output_width as Real / self.aspect_ratio) as u32
+    }
+
+    /// The number of samples every pixel takes, at most that many for adaptive renderers
+    pub fn sample_count(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    /// How many times a ray may bounce before it is given up
+    pub fn depth_limit(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Feeds every setting that changes where the rays go and how deep they are traced into
+    /// `state`. The samples per pixel and the gamma correction are left out.
+    pub fn hash_settings<H: Hasher>(&self, state: &mut H) {
+        let bits = |v: Vec3| v.to_array().map(Real::to_bits);
+
+        [
+            self.fov,
+            self.aspect_ratio,
+            self.defocus_angle,
+            self.focus_distance,
+        ]
+        .map(Real::to_bits)
+        .hash(state);
+        [self.pos, self.look_at, self.up].map(bits).hash(state);
+        self.max_depth.hash(state);
+    }
+
+    /// The pixel grid of an `output_width` pixels wide image
+    pub fn viewport(&self, output_width: u32) -> Viewport {
+        let back = (self.pos - self.look_at).normalize();
+        let right = self.up.cross(back).normalize();
+        let up = back.cross(right).normalize();
+
+        let h = self.focus_distance * (self.fov / 2.0).to_radians().tan();
+        let viewport_height = 2.0 * h;
+        let viewport_width = viewport_height * self.aspect_ratio;
+
+        let viewport_u = viewport_width * right;
+        let viewport_v = -viewport_height * up;
+
+        let output_height = self.output_height(output_width);
+        let pixel_delta_u = viewport_u / output_width as Real;
+        let pixel_delta_v = viewport_v / output_height as Real;
+
+        let defocus_disk = (self.defocus_angle > Real::EPSILON).then(|| {
+            let defocus_radius =
+                self.focus_distance * (self.defocus_angle / 2.0).to_radians().tan();
+            debug!("defocus_radius: {}", defocus_radius);
+            (defocus_radius * right, -defocus_radius * up)
+        });
+
+        let viewport_upper_left =
+            self.pos - self.focus_distance * back - viewport_u / 2.0 - viewport_v / 2.0;
+        let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+
+        Viewport {
+            pos: self.pos,
+            pixel00_loc,
+            pixel_delta_u,
+            pixel_delta_v,
+            defocus_disk,
+            spread: pixel_delta_v.length() / self.focus_distance,
+        }
+    }
+}
+
+impl fmt::Display for Camera {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "pos: {}, look_at: {}, up: {}",
+            self.pos, self.look_at, self.up
+        )?;
+        writeln!(
+            f,
+            "fov: {}, aspect ratio: {}, defocus angle: {}, focus distance: {}",
+            self.fov, self.aspect_ratio, self.defocus_angle, self.focus_distance
+        )?;
+        write!(
+            f,
+            "samples per pixel: {}, max depth: {}",
+            self.samples_per_pixel, self.max_depth
+        )
+    }
+}
+
+impl Camera {
+    /// Renders `ray_color(ray, max_depth)` averaged over the samples of every pixel to `path`
+    ///
+    /// With a single sample per pixel the ray goes through the pixel center.
+    pub fn render_to_path<F>(&self, output_width: u32, path: impl AsRef<Path>, ray_color: F)
+    where
+        F: Fn(&Ray, u32) -> Vec3 + Sync,
+    {
+        let output_height = self.output_height(output_width);
+        let viewport = self.viewport(output_width);
+
+        let calc_pixel_color = |x: u32, y: u32| {
+            let color = if self.samples_per_pixel == 1 {
+                ray_color(&viewport.center_ray(x, y), self.max_depth)
+            } else {
+                (0..self.samples_per_pixel)
+                    .into_par_iter()
+                    .map(|_| ray_color(&viewport.ray(x, y, &mut thread_rng()), self.max_depth))
+                    .sum::<Vec3>()
+                    / self.samples_per_pixel as Real
+            };
+
+            let color = if self.gamma_correction {
+                linear_to_gamma(color)
+            } else {
+                color
+            };
+
+            let color_u8 = (255.999 * color).clamp(Vec3::ZERO, Vec3::splat(255.0));
+            Rgb([color_u8.x as u8, color_u8.y as u8, color_u8.z as u8])
+        };
+
+        let mut image = RgbImage::new(output_width, output_height);
+        // 防止等半天渲染完了才写入失败，要是失败干脆就先失败，现在不失败后面应该也不失败（有点蠢的方法）
+        save(&image, path.as_ref()).unwrap();
+
+        let t = Instant::now();
+        info!("generating image...");
+        let multi = logger().multi();
+        let pb = multi.add(ProgressBar::new((output_height * output_width) as u64));
+        image.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
+            *pixel = calc_pixel_color(x, y);
+            pb.inc(1);
+        });
+        pb.finish();
+        multi.remove(&pb);
+
+        save(&image, path.as_ref()).unwrap();
+        info!("cost: {:?}", t.elapsed());
+    }
+}
+
+/// `image` writes PAM for `.ppm`, so those are written as the plain text pixmaps the first
+/// chapters used to write by hand
+fn save(image: &RgbImage, path: &Path) -> ImageResult<()> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ppm"))
+    {
+        let writer = BufWriter::new(File::create(path)?);
+        PnmEncoder::new(writer)
+            .with_subtype(PnmSubtype::Pixmap(SampleEncoding::Ascii))
+            .write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                ExtendedColorType::Rgb8,
+            )
+    } else {
+        image.save(path)
+    }
+}
+
+/// Values derived from the camera for one output size
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pos: Vec3,
+    pixel00_loc: Vec3,
+    pixel_delta_u: Vec3,
+    pixel_delta_v: Vec3,
+    /// The axes of the defocus disk, `None` for a pinhole camera
+    defocus_disk: Option<(Vec3, Vec3)>,
+    /// The angle one pixel covers, how fast the footprint of its rays widens
+    spread: Real,
+}
+
+impl Viewport {
+    pub fn pixel_center(&self, x: u32, y: u32) -> Vec3 {
+        self.pixel00_loc + (x as Real * self.pixel_delta_u) + (y as Real * self.pixel_delta_v)
+    }
+
+    /// The ray from the camera's position through the center of pixel `(x, y)`
+    pub fn center_ray(&self, x: u32, y: u32) -> Ray {
+        Ray::new(self.pos, self.pixel_center(x, y) - self.pos).with_spread(self.spread)
+    }
+
+    /// A ray through a random point of pixel `(x, y)`, from a random point of the defocus disk
+    pub fn ray(&self, x: u32, y: u32, rng: &mut dyn RngCore) -> Ray {
+        let rand_offset = (rng.gen::<Real>() - 0.5) * self.pixel_delta_u
+            + (rng.gen::<Real>() - 0.5) * self.pixel_delta_v;
+
+        let ray_origin = match self.defocus_disk {
+            None => self.pos,
+            Some((defocus_disk_u, defocus_disk_v)) => {
+                let rand_vec = random_in_unit_disk(rng);
+                self.pos + (defocus_disk_u * rand_vec.x) + (defocus_disk_v * rand_vec.y)
+            }
+        };
+        Ray::new(
+            ray_origin,
+            self.pixel_center(x, y) + rand_offset - ray_origin,
+        )
+        .with_spread(self.spread)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_viewport() {
+        // looking down -x with a 90 degree fov, the image plane at x = -10 is 40 by 20 units
+        let camera = Camera::new(2.0)
+            .pos(Vec3::ZERO)
+            .look_at(Vec3::NEG_X)
+            .focus_distance(10.0);
+        let viewport = camera.viewport(200);
+
+        let center = viewport.center_ray(0, 0);
+        assert!(center.origin.abs_diff_eq(Vec3::ZERO, 1e-6));
+        assert!(center
+            .direction
+            .abs_diff_eq(Vec3::new(-10.0, 9.9, 19.9), 1e-4));
+        // a pixel 0.2 units wide, 10 units away
+        assert!((center.spread - 0.02).abs() < 1e-6);
+
+        // the jitter stays within the pixel even though the camera is turned
+        let rng = &mut SmallRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let ray = viewport.ray(0, 0, rng);
+            assert_eq!(ray.origin, Vec3::ZERO);
+            let offset = ray.direction - center.direction;
+            assert!(offset.x.abs() < 1e-4);
+            assert!(offset.y.abs() <= 0.1 + 1e-4 && offset.z.abs() <= 0.1 + 1e-4);
+        }
+
+        let blurred = camera.defocus_angle(10.0).viewport(200);
+        let ray = blurred.ray(0, 0, rng);
+        assert!(ray.origin != Vec3::ZERO);
+        assert!(ray.origin.x.abs() < 1e-6);
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(feature = "sampling")]
+use std::hash::Hasher;
+use std::ops::Range;
+
+#[cfg(feature = "sampling")]
+use rand::RngCore;
+
+use crate::{
+    math::{Real, Vec3},
+    Ray,
+};
+
+/// `M` is whatever the crate hits with, e.g. a shared material, `()` when there is none
+#[derive(Clone)]
+pub struct HitRecord<M = ()> {
+    pub point: Vec3,
+    /// Unit normal vector
+    pub normal: Vec3,
+    pub t: Real,
+    pub front_face: bool,
+    /// `None` for objects that leave the material to whoever wraps them
+    pub material: Option<M>,
+    pub u: Real,
+    pub v: Real,
+    /// Unit vector along which `u` grows, zero for objects without uv coordinates
+    pub tangent: Vec3,
+    /// Unit vector along which `v` grows, zero for objects without uv coordinates
+    pub bitangent: Vec3,
+    /// Width of the ray's footprint in uv units, zero when unknown
+    pub footprint: Real,
+}
+
+pub trait Hittable<M = ()> {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord<M>>;
+}
+
+/// Objects that can be sampled as lights, for moving objects `time` is the time of the rays
+#[cfg(feature = "sampling")]
+pub trait Sampleable {
+    /// Density of sampling `direction` from `origin` with [`Sampleable::random`]
+    fn pdf_value(&self, origin: Vec3, direction: Vec3, time: Real) -> Real;
+
+    /// A random direction from `origin` towards the object
+    fn random(&self, origin: Vec3, time: Real, rng: &mut dyn RngCore) -> Vec3;
+
+    /// Feeds the shape of the object into `state`, to tell renders with other lights apart
+    fn hash_settings(&self, state: &mut dyn Hasher);
+}
+
+/// The closest hit of all the objects
+impl<M> Hittable<M> for Vec<Box<dyn Hittable<M> + Send + Sync>> {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord<M>> {
+        let mut closest = t_range.end;
+        let mut hit_record = None;
+        for object in self.iter() {
+            if let Some(record) = object.hit(ray, t_range.start..closest) {
+                closest = record.t;
+                hit_record = Some(record);
+            }
+        }
+        hit_record
+    }
+}
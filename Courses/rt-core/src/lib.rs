@@ -0,0 +1,23 @@
+//! What every chapter of the books shares: rays, the hittable interface, the bvh, the camera,
+//! the logger, the math helpers and the final scene of the first book.
+//!
+//! Only `Ray`, `Hittable`, the median split bvh and the non-random helpers are always built,
+//! the rest is behind the `log`, `rand`, `sampling` and `camera` features. The `f64` feature
+//! switches everything from `f32` to `f64`, see [`math`].
+
+pub mod bvh;
+#[cfg(feature = "camera")]
+pub mod camera;
+pub mod hittable;
+#[cfg(feature = "log")]
+pub mod log;
+pub mod math;
+pub mod ray;
+#[cfg(feature = "rand")]
+pub mod scene;
+pub mod utils;
+
+pub use hittable::{HitRecord, Hittable};
+#[cfg(feature = "sampling")]
+pub use hittable::Sampleable;
+pub use ray::Ray;
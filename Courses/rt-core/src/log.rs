@@ -28,5 +28,5 @@ impl Logger {
 
 pub fn logger() -> &'static Logger {
     static LOGGER: OnceLock<Logger> = OnceLock::new();
-    LOGGER.get_or_init(|| Logger::init())
+    LOGGER.get_or_init(Logger::init)
 }
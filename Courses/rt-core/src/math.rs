@@ -0,0 +1,43 @@
+//! The scalar and vector types everything is computed with.
+//!
+//! `f32` and glam's `Vec3` family by default, `f64` and the `DVec3` family with the `f64`
+//! feature, which keeps the hit points on huge or far away objects from sinking below their
+//! surface (shadow acne). Images stay `f32` either way.
+
+#[cfg(not(feature = "f64"))]
+mod precision {
+    pub use glam::{Affine3A as Affine3, Mat3A as Mat3, Quat, Vec2, Vec3};
+    pub use std::f32::consts;
+
+    pub type Real = f32;
+
+    /// The channels of a color as stored in an image
+    pub fn to_f32_array(v: Vec3) -> [f32; 3] {
+        v.to_array()
+    }
+
+    pub fn from_f32_array(a: [f32; 3]) -> Vec3 {
+        Vec3::from_array(a)
+    }
+}
+
+#[cfg(feature = "f64")]
+mod precision {
+    pub use glam::{
+        DAffine3 as Affine3, DMat3 as Mat3, DQuat as Quat, DVec2 as Vec2, DVec3 as Vec3,
+    };
+    pub use std::f64::consts;
+
+    pub type Real = f64;
+
+    /// The channels of a color as stored in an image
+    pub fn to_f32_array(v: Vec3) -> [f32; 3] {
+        v.as_vec3().to_array()
+    }
+
+    pub fn from_f32_array(a: [f32; 3]) -> Vec3 {
+        glam::Vec3::from_array(a).as_dvec3()
+    }
+}
+
+pub use precision::*;
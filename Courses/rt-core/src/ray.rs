@@ -0,0 +1,69 @@
+use crate::math::{Real, Vec3};
+
+#[derive(Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+    /// The moment the ray is cast, within the camera's shutter interval
+    pub time: Real,
+    /// How fast the ray's footprint widens with the distance travelled, 0 for a thin ray
+    pub spread: Real,
+    /// The only color channel the ray carries once it was dispersed, `None` for all three
+    pub channel: Option<usize>,
+    /// `1 / direction`, for the slab tests of every bvh node
+    inv_direction: Vec3,
+    /// 1 where the direction is negative
+    sign: [usize; 3],
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        let inv_direction = direction.recip();
+        let sign = inv_direction
+            .to_array()
+            .map(|x| x.is_sign_negative() as usize);
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+            spread: 0.0,
+            channel: None,
+            inv_direction,
+            sign,
+        }
+    }
+
+    pub fn with_time(mut self, time: Real) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Traces the ray as a cone, e.g. one pixel wide for camera rays
+    pub fn with_spread(mut self, spread: Real) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    /// Keeps every bounce of a path on the channel it was dispersed into
+    pub fn with_channel(mut self, channel: Option<usize>) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// The width of the footprint after `t`
+    pub fn width_at(&self, t: Real) -> Real {
+        self.spread * t * self.direction.length()
+    }
+
+    pub fn at(&self, t: Real) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    pub fn inv_direction(&self) -> Vec3 {
+        self.inv_direction
+    }
+
+    pub fn sign(&self) -> [usize; 3] {
+        self.sign
+    }
+}
@@ -0,0 +1,181 @@
+//! The final scene of "Ray Tracing in One Weekend" as plain data, which every crate turns into
+//! its own spheres and materials. The spheres can also be traced as they are, without
+//! materials, e.g. by benchmarks.
+
+use std::ops::Range;
+
+use rand::{Rng, RngCore};
+
+use crate::{
+    bvh::{Aabb, HasAabb},
+    math::{Real, Vec3},
+    HitRecord, Hittable, Ray,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterialDesc {
+    Lambertian { albedo: Vec3 },
+    Metal { albedo: Vec3, fuzz: Real },
+    Dielectric { refraction_index: Real },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SphereDesc {
+    pub center: Vec3,
+    pub radius: Real,
+    pub material: MaterialDesc,
+}
+
+/// The small random spheres and the three big ones, without the ground, whose material
+/// differs between the books
+pub fn final_scene(rng: &mut dyn RngCore) -> Vec<SphereDesc> {
+    let mut spheres = Vec::new();
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Vec3::new(
+                a as Real + 0.9 * rng.gen::<Real>(),
+                0.2,
+                b as Real + 0.9 * rng.gen::<Real>(),
+            );
+
+            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let choose_mat = rng.gen::<Real>();
+
+                let albedo = Vec3::new(rng.gen::<Real>(), rng.gen::<Real>(), rng.gen::<Real>())
+                    * Vec3::new(rng.gen::<Real>(), rng.gen::<Real>(), rng.gen::<Real>());
+                let material = if choose_mat < 0.8 {
+                    MaterialDesc::Lambertian { albedo }
+                } else if choose_mat < 0.95 {
+                    MaterialDesc::Metal {
+                        albedo,
+                        fuzz: rng.gen::<Real>() * 0.5,
+                    }
+                } else {
+                    MaterialDesc::Dielectric {
+                        refraction_index: 1.5,
+                    }
+                };
+
+                spheres.push(SphereDesc {
+                    center,
+                    radius: 0.2,
+                    material,
+                });
+            }
+        }
+    }
+
+    spheres.push(SphereDesc {
+        center: Vec3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: MaterialDesc::Dielectric {
+            refraction_index: 1.5,
+        },
+    });
+    spheres.push(SphereDesc {
+        center: Vec3::new(-4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: MaterialDesc::Lambertian {
+            albedo: Vec3::new(0.4, 0.2, 0.1),
+        },
+    });
+    spheres.push(SphereDesc {
+        center: Vec3::new(4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: MaterialDesc::Metal {
+            albedo: Vec3::new(0.7, 0.6, 0.5),
+            fuzz: 0.0,
+        },
+    });
+    spheres
+}
+
+impl Hittable for SphereDesc {
+    fn hit(&self, ray: &Ray, t_range: Range<Real>) -> Option<HitRecord> {
+        let oc = ray.origin - self.center;
+        let a = ray.direction.dot(ray.direction);
+        let b = oc.dot(ray.direction);
+        let c = oc.dot(oc) - self.radius * self.radius;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let mut t = (-b - sqrtd) / a;
+        if t < t_range.start || t_range.end < t {
+            t = (-b + sqrtd) / a;
+        }
+        if t < t_range.start || t_range.end < t {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let normal = (point - self.center) / self.radius;
+        let front_face = ray.direction.dot(normal) < 0.0;
+
+        Some(HitRecord {
+            point,
+            normal: if front_face { normal } else { -normal },
+            t,
+            front_face,
+            material: None,
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            footprint: 0.0,
+        })
+    }
+}
+
+impl HasAabb for SphereDesc {
+    fn aabb(&self) -> Aabb {
+        let r = Vec3::splat(self.radius);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::bvh::{AabbHittable, BvhNode};
+
+    use super::*;
+
+    #[test]
+    fn test_final_scene_bvh() {
+        let spheres = final_scene(&mut StdRng::seed_from_u64(0));
+        assert!(spheres.len() > 400);
+
+        let bvh = BvhNode::from_objects(
+            spheres
+                .iter()
+                .map(|&sphere| Box::new(sphere) as Box<dyn AabbHittable + Send + Sync>)
+                .collect(),
+        );
+        let stats = bvh.stats();
+        assert_eq!(stats.leaves, spheres.len());
+        assert_eq!(stats.nodes, spheres.len() - 1);
+
+        // the same closest hits as testing every sphere
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            let origin = Vec3::new(13.0, 2.0, 3.0) + Vec3::new(rng.gen(), rng.gen(), rng.gen());
+            let target = Vec3::new(
+                rng.gen_range(-11.0..11.0),
+                rng.gen_range(0.0..1.0),
+                rng.gen_range(-11.0..11.0),
+            );
+            let ray = Ray::new(origin, target - origin);
+            let expected = spheres
+                .iter()
+                .filter_map(|sphere| sphere.hit(&ray, 0.001..Real::INFINITY))
+                .map(|record| record.t)
+                .reduce(Real::min);
+            let actual = bvh.hit(&ray, 0.001..Real::INFINITY).map(|record| record.t);
+            assert_eq!(actual, expected);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(feature = "rand")]
+use rand::{rngs::SmallRng, Rng, RngCore, SeedableRng};
+
+#[cfg(feature = "rand")]
+use crate::math::consts::PI;
+use crate::math::{Real, Vec3};
+
+/// The random number generator of one sample of pixel `(x, y)`, the same arguments always
+/// give the same random numbers no matter which thread takes the sample
+#[cfg(feature = "rand")]
+pub fn sample_rng(seed: u64, x: u32, y: u32, sample: u32) -> SmallRng {
+    let mut state = seed;
+    for value in [x, y, sample] {
+        state = splitmix64(state ^ value as u64);
+    }
+    SmallRng::seed_from_u64(state)
+}
+
+/// The SplitMix64 finalizer, a cheap bijective hash of 64 bits
+pub fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(feature = "rand")]
+pub fn random_in_unit_sphere(rng: &mut dyn RngCore) -> Vec3 {
+    loop {
+        let p = Vec3::new(
+            rng.gen::<Real>() * 2.0 - 1.0,
+            rng.gen::<Real>() * 2.0 - 1.0,
+            rng.gen::<Real>() * 2.0 - 1.0,
+        );
+        if p.length_squared() >= Real::EPSILON && p.length_squared() - 1.0 <= Real::EPSILON {
+            return p;
+        }
+    }
+}
+
+#[cfg(feature = "rand")]
+pub fn random_in_unit_disk(rng: &mut dyn RngCore) -> Vec3 {
+    loop {
+        let p = Vec3::new(
+            rng.gen::<Real>() * 2.0 - 1.0,
+            rng.gen::<Real>() * 2.0 - 1.0,
+            0.0,
+        );
+        if p.length_squared() >= Real::EPSILON && p.length_squared() - 1.0 <= Real::EPSILON {
+            return p;
+        }
+    }
+}
+
+#[cfg(feature = "rand")]
+pub fn random_on_hemisphere(normal: Vec3, rng: &mut dyn RngCore) -> Vec3 {
+    let p = random_in_unit_sphere(rng).normalize();
+    if p.dot(normal) > 0.0 {
+        p
+    } else {
+        -p
+    }
+}
+
+/// Random direction around +z, with density `cos(theta) / PI`
+#[cfg(feature = "rand")]
+pub fn random_cosine_direction(rng: &mut dyn RngCore) -> Vec3 {
+    let r1 = rng.gen::<Real>();
+    let r2 = rng.gen::<Real>();
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+    let z = (1.0 - r2).sqrt();
+    Vec3::new(x, y, z)
+}
+
+pub fn linear_to_gamma(linear: Vec3) -> Vec3 {
+    linear.map(|x| if x > 0.0 { x.sqrt() } else { x })
+}
+
+/// Orthonormal basis whose `w` axis is the given direction
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(w: Vec3) -> Self {
+        let w = w.normalize();
+        let a = if w.x.abs() > 0.9 { Vec3::Y } else { Vec3::X };
+        let v = w.cross(a).normalize();
+        let u = w.cross(v);
+        Onb { u, v, w }
+    }
+
+    /// local coordinates -> world coordinates
+    pub fn transform(&self, local: Vec3) -> Vec3 {
+        local.x * self.u + local.y * self.v + local.z * self.w
+    }
+}
+
+pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: Real) -> Vec3 {
+    let cos_theta = (-uv).dot(n).min(1.0);
+    let r_out_perp = etai_over_etat * (uv + cos_theta * n);
+    let r_out_parallel = -(1.0 - r_out_perp.length_squared()).sqrt() * n;
+    r_out_perp + r_out_parallel
+}
+
+/// Schlick's approximation for reflectance
+pub fn reflectance(cosine: Real, ref_idx: Real) -> Real {
+    let r0 = (ref_idx - 1.0) / (ref_idx + 1.0);
+    let r0 = r0 * r0;
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+}